@@ -0,0 +1,293 @@
+/// Implementation of DataContainer for a sorted Vec augmented with a segment tree of aggregates
+use std::iter::{FromIterator, Map};
+use std::ops::Sub;
+use std::slice;
+use num_traits::{Float, ToPrimitive};
+use crate::interface::{DataContainer, DataContainerMut, AggregateContainer, Aggregate, Interpolation};
+use super::{SortedVec, KeyRef};
+
+/// Aggregates of the data keys of a node of the tree, and of the segments between them
+#[derive(Debug, Clone, Copy)]
+struct Node<V> {
+    min: V,
+    max: V,
+    step: V,
+    linear: V
+}
+
+/// Data keys held in a [SortedVec](struct.SortedVec.html), with a segment tree of their minimum, maximum
+/// and integrals, so the aggregates of a time range are computed in O(log n)
+/// (ex: telemetry dashboards querying long recordings)
+///
+/// The values are scalar floats. The tree has room for the next power of two of data keys:
+/// appending a data key after the last one, or replacing the value of a data key, updates the tree in O(log n)
+/// (amortized, the tree is rebuilt when it is full), so recording keys one by one is O(n log n).
+/// Inserting a data key before the last one or removing data keys rebuilds the tree, which is O(n).
+#[derive(Debug, Clone)]
+pub struct AggregateVec<T,V> {
+    keys: SortedVec<T,V>,
+    /// The nodes of a complete tree over `leaves` data keys, None for the nodes after the last data key
+    nodes: Vec<Option<Node<V>>>,
+    leaves: usize
+}
+
+impl<T,V> AggregateVec<T,V>
+where
+    T: Ord + Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    pub fn new() -> Self {
+        AggregateVec {
+            keys: SortedVec::new(),
+            nodes: Vec::new(),
+            leaves: 0
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Iterator of the data keys (oldest first)
+    pub fn iter(&self) -> Map<slice::Iter<'_,(T,V)>, KeyRef<'_,T,V>> {
+        self.keys.iter()
+    }
+
+    /// The data keys (oldest first)
+    pub fn as_slice(&self) -> &[(T,V)] {
+        self.keys.as_slice()
+    }
+
+    /// Add a data key, returning the value it replaced if there was already a key at instant `time`
+    pub fn insert(&mut self, time: T, value: V) -> Option<V> {
+        let index = self.keys.as_slice().binary_search_by(|(key, _)| key.cmp(&time));
+        let replaced = self.keys.insert(time, value);
+        match index {
+            Ok(index) => self.update(index),
+            Err(index) if index + 1 == self.keys.len() && index < self.leaves => self.update(index),
+            Err(_) => self.build()
+        }
+        replaced
+    }
+
+    /// Remove the data key at instant `time` if any
+    pub fn remove(&mut self, time: &T) -> Option<V> {
+        let removed = self.keys.remove(time)?;
+        self.build();
+        Some(removed)
+    }
+
+    /// Remove every data key strictly before instant `time`
+    pub fn remove_before(&mut self, time: &T) {
+        self.keys.remove_before(time);
+        self.build();
+    }
+
+    fn build(&mut self) {
+        self.leaves = self.keys.len().next_power_of_two();
+        self.nodes.clear();
+        if !self.keys.is_empty() {
+            self.nodes.resize(2 * self.leaves - 1, None);
+            self.build_node(0, 0, self.leaves - 1);
+        }
+    }
+
+    fn build_node(&mut self, node: usize, first: usize, last: usize) -> Option<Node<V>> {
+        let aggregate = if first >= self.keys.len() {
+            None
+        } else if first == last {
+            Some(self.leaf(first))
+        } else {
+            let middle = (first + last) / 2;
+            self.build_node(2 * node + 1, first, middle);
+            self.build_node(2 * node + 2, middle + 1, last);
+            self.children(node, middle)
+        };
+        self.nodes[node] = aggregate;
+        aggregate
+    }
+
+    /// Update the nodes above the data key at `index`, after it was set or appended
+    fn update(&mut self, index: usize) {
+        let (mut node, mut first, mut last) = (0, 0, self.leaves - 1);
+        let mut path = Vec::new();
+        while first < last {
+            let middle = (first + last) / 2;
+            path.push((node, middle));
+            if index <= middle {
+                node = 2 * node + 1;
+                last = middle;
+            } else {
+                node = 2 * node + 2;
+                first = middle + 1;
+            }
+        }
+        self.nodes[node] = Some(self.leaf(index));
+        for (node, middle) in path.into_iter().rev() {
+            self.nodes[node] = self.children(node, middle);
+        }
+    }
+
+    fn leaf(&self, index: usize) -> Node<V> {
+        let value = self.keys.as_slice()[index].1;
+        Node { min: value, max: value, step: V::zero(), linear: V::zero() }
+    }
+
+    /// Aggregates of a node from its children, split after the data key at `middle`
+    fn children(&self, node: usize, middle: usize) -> Option<Node<V>> {
+        match (self.nodes[2 * node + 1], self.nodes[2 * node + 2]) {
+            (Some(left), Some(right)) => Some(self.join(left, middle, right)),
+            (left, _) => left
+        }
+    }
+
+    /// Aggregates of the node before the data key at `index` and of the node after it
+    fn join(&self, left: Node<V>, index: usize, right: Node<V>) -> Node<V> {
+        let keys = self.keys.as_slice();
+        let (start, end) = (&keys[index], &keys[index + 1]);
+        let (start, end) = ((&start.0, &start.1), (&end.0, &end.1));
+        Node {
+            min: left.min.min(right.min),
+            max: left.max.max(right.max),
+            step: left.step + Interpolation::Step.integral(start, end) + right.step,
+            linear: left.linear + Interpolation::Linear.integral(start, end) + right.linear
+        }
+    }
+
+    /// Aggregates of the data keys from index `first` to `last`, in the node covering `node_first` to `node_last`
+    fn query(&self, node: usize, node_first: usize, node_last: usize, first: usize, last: usize) -> Option<(Node<V>, usize, usize)> {
+        if last < node_first || node_last < first {
+            return None;
+        }
+        if first <= node_first && node_last <= last {
+            return self.nodes[node].map(|aggregate| (aggregate, node_first, node_last));
+        }
+        let middle = (node_first + node_last) / 2;
+        match (self.query(2 * node + 1, node_first, middle, first, last), self.query(2 * node + 2, middle + 1, node_last, first, last)) {
+            (Some((left, start, join)), Some((right, _, end))) => Some((self.join(left, join, right), start, end)),
+            (left, right) => left.or(right)
+        }
+    }
+}
+
+impl<T,V> Default for AggregateVec<T,V>
+where
+    T: Ord + Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    fn default() -> Self {
+        AggregateVec::new()
+    }
+}
+
+/// Keeps the last data key given at each instant, like a BTreeMap
+impl<T,V> FromIterator<(T,V)> for AggregateVec<T,V>
+where
+    T: Ord + Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    fn from_iter<I: IntoIterator<Item = (T,V)>>(keys: I) -> Self {
+        let mut aggregates = AggregateVec {
+            keys: keys.into_iter().collect(),
+            nodes: Vec::new(),
+            leaves: 0
+        };
+        aggregates.build();
+        aggregates
+    }
+}
+
+impl<'a,T,V> IntoIterator for &'a AggregateVec<T,V> {
+    type Item = (&'a T, &'a V);
+    type IntoIter = Map<slice::Iter<'a,(T,V)>, KeyRef<'a,T,V>>;
+    fn into_iter(self) -> Self::IntoIter {
+        (&self.keys).into_iter()
+    }
+}
+
+impl<T: Ord, V> DataContainer<T,V> for AggregateVec<T,V> {
+    type Past<'a> = <SortedVec<T,V> as DataContainer<T,V>>::Past<'a> where T: 'a, V: 'a;
+    type Future<'a> = <SortedVec<T,V> as DataContainer<T,V>>::Future<'a> where T: 'a, V: 'a;
+    fn split_at<'a>(&'a self, time: &T) -> (Self::Past<'a>, Self::Future<'a>) {
+        self.keys.split_at(time)
+    }
+}
+
+impl<T,V> DataContainerMut<T,V> for AggregateVec<T,V>
+where
+    T: Ord + Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    fn insert_key(&mut self, time: T, value: V) -> Option<V> {
+        self.insert(time, value)
+    }
+    fn remove_key(&mut self, time: &T) -> Option<V> {
+        self.remove(time)
+    }
+    fn remove_before(&mut self, time: &T) {
+        self.remove_before(time);
+    }
+}
+
+impl<T,V> AggregateContainer<T,V> for AggregateVec<T,V>
+where
+    T: Ord + Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    fn aggregate_keys(&self, start: &T, end: &T, interpolation: Interpolation) -> Option<Aggregate<T,V>> {
+        let keys = self.keys.as_slice();
+        let first = keys.partition_point(|(time, _)| time <= start);
+        let last = keys.partition_point(|(time, _)| time < end).checked_sub(1)?;
+        if first > last {
+            return None;
+        }
+        let (node, _, _) = self.query(0, 0, self.leaves - 1, first, last)?;
+        Some(Aggregate {
+            first: keys[first].clone(),
+            last: keys[last].clone(),
+            min: node.min,
+            max: node.max,
+            integral: match interpolation {
+                Interpolation::Step => node.step,
+                Interpolation::Linear => node.linear
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_rebuild_the_tree() {
+        let mut keys: AggregateVec<u32, f64> = vec![(0, 1.0), (10, 3.0), (20, 2.0)].into_iter().collect();
+        let aggregate = keys.aggregate_keys(&0, &30, Interpolation::Linear).unwrap();
+        assert_eq!((aggregate.first, aggregate.last, aggregate.min, aggregate.max, aggregate.integral), ((10, 3.0), (20, 2.0), 2.0, 3.0, 25.0));
+        keys.insert(15, -1.0);
+        let aggregate = keys.aggregate_keys(&0, &30, Interpolation::Step).unwrap();
+        assert_eq!((aggregate.min, aggregate.integral), (-1.0, 10.0));
+        keys.remove(&15);
+        keys.remove_before(&10);
+        assert_eq!(keys.aggregate_keys(&0, &30, Interpolation::Step).unwrap().integral, 30.0);
+        assert!(keys.aggregate_keys(&10, &20, Interpolation::Step).is_none());
+    }
+
+    #[test]
+    fn appends_update_the_tree() {
+        let keys: Vec<(u32, f64)> = (0..37u32).map(|t| (t * 2, f64::from((t * 13) % 7) - 3.0)).collect();
+        let mut appended = AggregateVec::new();
+        for (count, (time, value)) in keys.iter().enumerate() {
+            appended.insert(*time, *value);
+            let built: AggregateVec<u32, f64> = keys[..=count].iter().cloned().collect();
+            for (start, end) in [(0, 100), (1, 9), (5, 40), (30, 31)] {
+                for interpolation in [Interpolation::Step, Interpolation::Linear] {
+                    assert_eq!(appended.aggregate_keys(&start, &end, interpolation), built.aggregate_keys(&start, &end, interpolation));
+                }
+            }
+        }
+        // replacing a value updates the tree too
+        appended.insert(10, 50.0);
+        assert_eq!(appended.aggregate_keys(&0, &100, Interpolation::Linear).unwrap().max, 50.0);
+        assert_eq!(appended.aggregate_keys(&11, &100, Interpolation::Linear).unwrap().max, 3.0);
+    }
+}
@@ -1,15 +1,49 @@
 /// Implementation of DataContainer for BTreeMap
 pub use std::collections::BTreeMap;
 use std::cmp::Ord;
-use crate::interface::DataContainer;
-
-impl<'a, T: Clone+Ord, V: Clone> DataContainer<'a,T,V> for BTreeMap<T,V> {
-    fn split_at(&'a self, time: T) -> (Box<Iterator<Item = (T,V)> + 'a>, Box<Iterator<Item = (T,V)> + 'a>) {
-        (Box::new(self.range(..&time)
-            .rev()
-            .map(|(t,v)| (t.clone(),v.clone()))), 
-         Box::new(self.range(&time..)
-            .map(|(t,v)| (t.clone(),v.clone()))))
+use std::collections::btree_map::Range;
+use std::iter::Rev;
+use crate::interface::{DataContainer, DataContainerMut, AggregateContainer};
+#[cfg(feature = "serde")]
+use crate::interface::SerdeKeys;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use super::SortedVec;
+
+impl<T: Ord, V> DataContainer<T,V> for BTreeMap<T,V> {
+    type Past<'a> = Rev<Range<'a,T,V>> where T: 'a, V: 'a;
+    type Future<'a> = Range<'a,T,V> where T: 'a, V: 'a;
+    fn split_at<'a>(&'a self, time: &T) -> (Self::Past<'a>, Self::Future<'a>) {
+        (self.range(..time).rev(), self.range(time..))
+    }
+}
+
+impl<T: Ord, V> AggregateContainer<T,V> for BTreeMap<T,V> {}
+
+impl<T: Ord, V> DataContainerMut<T,V> for BTreeMap<T,V> {
+    fn insert_key(&mut self, time: T, value: V) -> Option<V> {
+        self.insert(time, value)
+    }
+    fn remove_key(&mut self, time: &T) -> Option<V> {
+        self.remove(time)
+    }
+    fn remove_before(&mut self, time: &T) {
+        *self = self.split_off(time);
+    }
+}
+
+/// Serialized as a list of `[time, value]` instead of the map representation of serde
+#[cfg(feature = "serde")]
+impl<T: Ord, V> SerdeKeys<T,V> for BTreeMap<T,V> {
+    fn serialize_keys<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where T: Serialize, V: Serialize {
+        serializer.collect_seq(self.iter())
+    }
+
+    fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    where T: Deserialize<'de>, V: Deserialize<'de> {
+        Ok(SortedVec::deserialize(deserializer)?.into_vec().into_iter().collect())
     }
 }
 
@@ -24,11 +58,11 @@ mod tests {
         bmap.insert(20, 3);
         bmap.insert(25, 4);
 
-        let (mut past, mut future) = bmap.split_at(17);
-        assert_eq!(past.next(), Some((15,2)));
-        assert_eq!(past.next(), Some((10,1)));
+        let (mut past, mut future) = bmap.split_at(&17);
+        assert_eq!(past.next(), Some((&15,&2)));
+        assert_eq!(past.next(), Some((&10,&1)));
 
-        assert_eq!(future.next(), Some((20,3)));
-        assert_eq!(future.next(), Some((25,4)));
+        assert_eq!(future.next(), Some((&20,&3)));
+        assert_eq!(future.next(), Some((&25,&4)));
     }
 }
\ No newline at end of file
@@ -2,5 +2,18 @@
  * Contains [DataContainer](../interface/trait.DataContainer.html) implementations
  */
 pub use self::btreemap::BTreeMap;
+pub use self::ring_buffer::RingBuffer;
+pub use self::sorted_vec::{SortedVec, Duplicates, DuplicateTime};
+pub use self::aggregate_vec::AggregateVec;
 
-mod btreemap;
\ No newline at end of file
+mod btreemap;
+mod ring_buffer;
+mod sorted_vec;
+mod aggregate_vec;
+
+/// Borrow a data key stored as a tuple, as returned by `DataContainer::split_at`
+type KeyRef<'a,T,V> = fn(&'a (T,V)) -> (&'a T, &'a V);
+
+fn key_ref<T,V>((time, value): &(T,V)) -> (&T, &V) {
+    (time, value)
+}
@@ -0,0 +1,282 @@
+/// Implementation of DataContainer for a fixed capacity ring buffer
+use std::collections::vec_deque::{self, VecDeque};
+use std::iter::{Map, Rev};
+use std::mem;
+use crate::interface::{DataContainer, DataContainerMut, AggregateContainer};
+#[cfg(feature = "serde")]
+use crate::interface::SerdeKeys;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer, ser::SerializeStruct, de::Error as DeError};
+#[cfg(feature = "serde")]
+use super::SortedVec;
+use super::{KeyRef, key_ref};
+
+/// Sorted data keys held in a ring buffer of fixed capacity
+///
+/// When the buffer is full, adding a data key evicts the oldest one, so the buffer can
+/// hold a stream of keys (ex: network snapshots) without growing.
+/// Appending a key more recent than all the others is O(1), inserting an older key
+/// falls back to a binary search and shifting the keys around it.
+pub struct RingBuffer<T,V> {
+    keys: VecDeque<(T,V)>,
+    capacity: usize
+}
+
+impl<T: Ord, V> RingBuffer<T,V> {
+    /// Empty buffer holding at most `capacity` data keys
+    ///
+    /// Panics if `capacity` is 0
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "a ring buffer needs to hold at least one data key");
+        RingBuffer {
+            keys: VecDeque::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.keys.len() == self.capacity
+    }
+
+    /// Oldest data key
+    pub fn first(&self) -> Option<(&T, &V)> {
+        self.keys.front().map(key_ref)
+    }
+
+    /// Most recent data key
+    pub fn last(&self) -> Option<(&T, &V)> {
+        self.keys.back().map(key_ref)
+    }
+
+    /// Iterator of the data keys (oldest first)
+    pub fn iter(&self) -> Map<vec_deque::Iter<'_,(T,V)>, KeyRef<'_,T,V>> {
+        self.keys.iter().map(key_ref as KeyRef<T,V>)
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// Add a data key, returning the value it replaced if there was already a key at instant `time`.
+    ///
+    /// If the buffer is full, the oldest data key is evicted, so a key older than every
+    /// data key of a full buffer is not added.
+    pub fn insert(&mut self, time: T, value: V) -> Option<V> {
+        let index = match self.keys.back() {
+            // Fast path, appending a more recent key
+            Some((last, _)) if *last < time => self.keys.len(),
+            None => 0,
+            _ => match self.keys.binary_search_by(|(t, _)| t.cmp(&time)) {
+                Ok(index) => return Some(mem::replace(&mut self.keys[index].1, value)),
+                Err(0) if self.is_full() => return None,
+                Err(index) => index
+            }
+        };
+        if self.is_full() {
+            self.keys.pop_front();
+            self.keys.insert(index - 1, (time, value));
+        } else {
+            self.keys.insert(index, (time, value));
+        }
+        None
+    }
+
+    /// Remove the data key at instant `time` if any
+    pub fn remove(&mut self, time: &T) -> Option<V> {
+        let index = self.keys.binary_search_by(|(t, _)| t.cmp(time)).ok()?;
+        self.keys.remove(index).map(|(_, value)| value)
+    }
+
+    /// Remove every data key strictly before instant `time`
+    pub fn remove_before(&mut self, time: &T) {
+        let index = self.keys.partition_point(|(t, _)| t < time);
+        self.keys.drain(..index);
+    }
+}
+
+impl<'a,T,V> IntoIterator for &'a RingBuffer<T,V> {
+    type Item = (&'a T, &'a V);
+    type IntoIter = Map<vec_deque::Iter<'a,(T,V)>, KeyRef<'a,T,V>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter().map(key_ref as KeyRef<T,V>)
+    }
+}
+
+impl<T: Ord, V> DataContainer<T,V> for RingBuffer<T,V> {
+    type Past<'a> = Rev<Map<vec_deque::Iter<'a,(T,V)>, KeyRef<'a,T,V>>> where T: 'a, V: 'a;
+    type Future<'a> = Map<vec_deque::Iter<'a,(T,V)>, KeyRef<'a,T,V>> where T: 'a, V: 'a;
+    fn split_at<'a>(&'a self, time: &T) -> (Self::Past<'a>, Self::Future<'a>) {
+        let index = self.keys.partition_point(|(t, _)| t < time);
+        (self.keys.range(..index).map(key_ref as KeyRef<T,V>).rev(),
+         self.keys.range(index..).map(key_ref as KeyRef<T,V>))
+    }
+}
+
+impl<T: Ord, V> AggregateContainer<T,V> for RingBuffer<T,V> {}
+
+impl<T: Ord, V> DataContainerMut<T,V> for RingBuffer<T,V> {
+    fn insert_key(&mut self, time: T, value: V) -> Option<V> {
+        self.insert(time, value)
+    }
+    fn remove_key(&mut self, time: &T) -> Option<V> {
+        self.remove(time)
+    }
+    fn remove_before(&mut self, time: &T) {
+        self.remove_before(time);
+    }
+}
+
+/// Serialized as a struct of the `capacity` and the `keys`, a list of `[time, value]` sorted by time
+#[cfg(feature = "serde")]
+impl<T: Serialize, V: Serialize> Serialize for RingBuffer<T,V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("RingBuffer", 2)?;
+        state.serialize_field("capacity", &self.capacity)?;
+        state.serialize_field("keys", &self.keys)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(rename = "RingBuffer", bound(deserialize = "T: Ord + Deserialize<'de>, V: Deserialize<'de>"))]
+struct RingBufferKeys<T,V> {
+    capacity: usize,
+    keys: SortedVec<T,V>
+}
+
+/// Deserialized from a struct of the `capacity` and the `keys` in any order,
+/// more keys than the capacity or 2 keys at the same instant are an error
+#[cfg(feature = "serde")]
+impl<'de, T: Ord + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for RingBuffer<T,V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RingBufferKeys { capacity, keys } = RingBufferKeys::deserialize(deserializer)?;
+        if capacity == 0 {
+            return Err(D::Error::custom("a ring buffer needs to hold at least one data key"));
+        }
+        if keys.len() > capacity {
+            return Err(D::Error::invalid_length(keys.len(), &"at most capacity data keys"));
+        }
+        let mut buffer = RingBuffer::with_capacity(capacity);
+        buffer.keys.extend(keys.into_vec());
+        Ok(buffer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Ord, V> SerdeKeys<T,V> for RingBuffer<T,V> {
+    fn serialize_keys<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where T: Serialize, V: Serialize {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    where T: Deserialize<'de>, V: Deserialize<'de> {
+        RingBuffer::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::TimeLine;
+    use crate::samplers::LerpSampler;
+    use crate::timelines::Timeline;
+
+    fn times(buffer: &RingBuffer<i32, i32>) -> Vec<i32> {
+        buffer.iter().map(|(t, _)| *t).collect()
+    }
+
+    #[test]
+    fn append_evicts_oldest() {
+        let mut buffer = RingBuffer::with_capacity(3);
+        for t in 0..5 {
+            assert_eq!(buffer.insert(t, t * 10), None);
+        }
+        assert_eq!(times(&buffer), vec![2, 3, 4]);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.first(), Some((&2, &20)));
+        assert_eq!(buffer.last(), Some((&4, &40)));
+    }
+
+    #[test]
+    fn out_of_order_insert() {
+        let mut buffer = RingBuffer::with_capacity(4);
+        buffer.insert(10, 1);
+        buffer.insert(30, 3);
+        buffer.insert(20, 2);
+        assert_eq!(buffer.insert(20, 4), Some(2));
+        assert_eq!(times(&buffer), vec![10, 20, 30]);
+        buffer.insert(5, 0);
+        buffer.insert(25, 5);
+        assert_eq!(times(&buffer), vec![10, 20, 25, 30]);
+        buffer.insert(0, 0);
+        assert_eq!(times(&buffer), vec![10, 20, 25, 30]);
+        assert_eq!(buffer.remove(&25), Some(5));
+        assert_eq!(buffer.remove(&25), None);
+        assert_eq!(times(&buffer), vec![10, 20, 30]);
+        buffer.remove_before(&20);
+        assert_eq!(times(&buffer), vec![20, 30]);
+    }
+
+    #[test]
+    fn split() {
+        let mut buffer = RingBuffer::with_capacity(4);
+        buffer.insert(10, 1);
+        buffer.insert(15, 2);
+        buffer.insert(20, 3);
+        buffer.insert(25, 4);
+
+        let (mut past, mut future) = buffer.split_at(&20);
+        assert_eq!(past.next(), Some((&15,&2)));
+        assert_eq!(past.next(), Some((&10,&1)));
+        assert_eq!(past.next(), None);
+
+        assert_eq!(future.next(), Some((&20,&3)));
+        assert_eq!(future.next(), Some((&25,&4)));
+        assert_eq!(future.next(), None);
+    }
+
+    #[test]
+    fn timeline() {
+        let mut timeline = Timeline::<_, LerpSampler, _, _>::new(RingBuffer::with_capacity(2));
+        timeline.set_key(0, 0.0);
+        timeline.set_key(10, 10.0);
+        timeline.set_key(20, 30.0);
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline.get_sample(5), Some(0.0));
+        assert_eq!(timeline.get_sample(15), Some(20.0));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg(test)]
+mod tests_serde {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let mut buffer = RingBuffer::with_capacity(3);
+        buffer.insert(10, 'b');
+        buffer.insert(0, 'a');
+        let json = serde_json::to_string(&buffer).unwrap();
+        assert_eq!(json, r#"{"capacity":3,"keys":[[0,"a"],[10,"b"]]}"#);
+        let buffer: RingBuffer<i32, char> = serde_json::from_str(&json).unwrap();
+        assert_eq!(buffer.capacity(), 3);
+        assert_eq!(buffer.iter().collect::<Vec<_>>(), vec![(&0, &'a'), (&10, &'b')]);
+        assert!(serde_json::from_str::<RingBuffer<i32, char>>(r#"{"capacity":1,"keys":[[0,"a"],[10,"b"]]}"#).is_err());
+        assert!(serde_json::from_str::<RingBuffer<i32, char>>(r#"{"capacity":0,"keys":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+/// Implementation of DataContainer for a sorted Vec
+use std::error::Error;
+use std::fmt;
+use std::iter::{FromIterator, Map, Rev};
+use std::mem;
+use std::slice;
+use crate::interface::{DataContainer, DataContainerMut, AggregateContainer};
+#[cfg(feature = "serde")]
+use crate::interface::SerdeKeys;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer, de::Error as DeError};
+use super::{KeyRef, key_ref};
+
+/// Data keys held contiguously in a Vec sorted by time
+///
+/// Sampling uses a binary search, and the keys are compact in memory, which suits small
+/// and mostly static timelines (ex: animation curves).
+/// Inserting or removing a data key shifts all the more recent keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortedVec<T,V> {
+    keys: Vec<(T,V)>
+}
+
+/// How to resolve data keys at the same instant when building a [SortedVec](struct.SortedVec.html)
+/// from unsorted data keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Keep the data key that came first
+    KeepFirst,
+    /// Keep the data key that came last, like inserting the data keys one by one
+    KeepLast,
+    /// Fail with a [DuplicateTime](struct.DuplicateTime.html) error
+    Reject
+}
+
+/// Two data keys are at the same instant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateTime<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Display for DuplicateTime<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "several data keys at instant {:?}", self.0)
+    }
+}
+
+impl<T: fmt::Debug> Error for DuplicateTime<T> {}
+
+impl<T: Ord, V> SortedVec<T,V> {
+    pub fn new() -> Self {
+        SortedVec {
+            keys: Vec::new()
+        }
+    }
+
+    /// Build from data keys in any order, with a single sort
+    pub fn from_keys<I>(keys: I, duplicates: Duplicates) -> Result<Self, DuplicateTime<T>>
+    where I: IntoIterator<Item = (T,V)> {
+        let mut keys: Vec<(T,V)> = keys.into_iter().collect();
+        // stable, so the data keys at the same instant stay in the order they came
+        keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if duplicates == Duplicates::Reject {
+            if let Some(index) = keys.windows(2).position(|pair| pair[0].0 == pair[1].0) {
+                return Err(DuplicateTime(keys.swap_remove(index).0));
+            }
+        }
+        keys.dedup_by(|next, kept| {
+            let duplicate = next.0 == kept.0;
+            if duplicate && duplicates == Duplicates::KeepLast {
+                mem::swap(next, kept);
+            }
+            duplicate
+        });
+        Ok(SortedVec { keys })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Oldest data key
+    pub fn first(&self) -> Option<(&T, &V)> {
+        self.keys.first().map(key_ref)
+    }
+
+    /// Most recent data key
+    pub fn last(&self) -> Option<(&T, &V)> {
+        self.keys.last().map(key_ref)
+    }
+
+    /// Iterator of the data keys (oldest first)
+    pub fn iter(&self) -> Map<slice::Iter<'_,(T,V)>, KeyRef<'_,T,V>> {
+        self.keys.iter().map(key_ref as KeyRef<T,V>)
+    }
+
+    /// The data keys (oldest first)
+    pub fn as_slice(&self) -> &[(T,V)] {
+        &self.keys
+    }
+
+    /// Get back the data keys (oldest first)
+    pub fn into_vec(self) -> Vec<(T,V)> {
+        self.keys
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// Add a data key, returning the value it replaced if there was already a key at instant `time`
+    pub fn insert(&mut self, time: T, value: V) -> Option<V> {
+        match self.keys.binary_search_by(|(t, _)| t.cmp(&time)) {
+            Ok(index) => Some(mem::replace(&mut self.keys[index].1, value)),
+            Err(index) => {
+                self.keys.insert(index, (time, value));
+                None
+            }
+        }
+    }
+
+    /// Remove the data key at instant `time` if any
+    pub fn remove(&mut self, time: &T) -> Option<V> {
+        let index = self.keys.binary_search_by(|(t, _)| t.cmp(time)).ok()?;
+        Some(self.keys.remove(index).1)
+    }
+
+    /// Remove every data key strictly before instant `time`
+    pub fn remove_before(&mut self, time: &T) {
+        let index = self.keys.partition_point(|(t, _)| t < time);
+        self.keys.drain(..index);
+    }
+}
+
+impl<T: Ord, V> Default for SortedVec<T,V> {
+    fn default() -> Self {
+        SortedVec::new()
+    }
+}
+
+impl<'a,T,V> IntoIterator for &'a SortedVec<T,V> {
+    type Item = (&'a T, &'a V);
+    type IntoIter = Map<slice::Iter<'a,(T,V)>, KeyRef<'a,T,V>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter().map(key_ref as KeyRef<T,V>)
+    }
+}
+
+/// Keeps the last data key given at each instant, like a BTreeMap
+impl<T: Ord, V> FromIterator<(T,V)> for SortedVec<T,V> {
+    fn from_iter<I: IntoIterator<Item = (T,V)>>(keys: I) -> Self {
+        match SortedVec::from_keys(keys, Duplicates::KeepLast) {
+            Ok(sorted) => sorted,
+            Err(_) => unreachable!("duplicates are resolved with KeepLast")
+        }
+    }
+}
+
+impl<T: Ord, V> DataContainer<T,V> for SortedVec<T,V> {
+    type Past<'a> = Rev<Map<slice::Iter<'a,(T,V)>, KeyRef<'a,T,V>>> where T: 'a, V: 'a;
+    type Future<'a> = Map<slice::Iter<'a,(T,V)>, KeyRef<'a,T,V>> where T: 'a, V: 'a;
+    fn split_at<'a>(&'a self, time: &T) -> (Self::Past<'a>, Self::Future<'a>) {
+        let (past, future) = self.keys.split_at(self.keys.partition_point(|(t, _)| t < time));
+        (past.iter().map(key_ref as KeyRef<T,V>).rev(),
+         future.iter().map(key_ref as KeyRef<T,V>))
+    }
+}
+
+impl<T: Ord, V> AggregateContainer<T,V> for SortedVec<T,V> {}
+
+impl<T: Ord, V> DataContainerMut<T,V> for SortedVec<T,V> {
+    fn insert_key(&mut self, time: T, value: V) -> Option<V> {
+        self.insert(time, value)
+    }
+    fn remove_key(&mut self, time: &T) -> Option<V> {
+        self.remove(time)
+    }
+    fn remove_before(&mut self, time: &T) {
+        self.remove_before(time);
+    }
+}
+
+/// Serialized as a list of `[time, value]` sorted by time
+#[cfg(feature = "serde")]
+impl<T: Serialize, V: Serialize> Serialize for SortedVec<T,V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.keys.serialize(serializer)
+    }
+}
+
+/// Deserialized from a list of `[time, value]` in any order, 2 keys at the same instant are an error
+#[cfg(feature = "serde")]
+impl<'de, T: Ord + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for SortedVec<T,V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SortedVec::from_keys(Vec::deserialize(deserializer)?, Duplicates::Reject)
+            .map_err(|_| D::Error::custom("several data keys at the same instant"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Ord, V> SerdeKeys<T,V> for SortedVec<T,V> {
+    fn serialize_keys<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where T: Serialize, V: Serialize {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    where T: Deserialize<'de>, V: Deserialize<'de> {
+        SortedVec::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::TimeLine;
+    use crate::samplers::StepSampler;
+    use crate::timelines::Timeline;
+
+    fn unsorted() -> Vec<(i32, char)> {
+        vec![(20, 'a'), (10, 'b'), (20, 'c'), (15, 'd'), (10, 'e')]
+    }
+
+    #[test]
+    fn build_from_unsorted() {
+        let first = SortedVec::from_keys(unsorted(), Duplicates::KeepFirst).unwrap();
+        assert_eq!(first.as_slice(), &[(10, 'b'), (15, 'd'), (20, 'a')]);
+        let last = SortedVec::from_keys(unsorted(), Duplicates::KeepLast).unwrap();
+        assert_eq!(last.as_slice(), &[(10, 'e'), (15, 'd'), (20, 'c')]);
+        assert_eq!(SortedVec::from_iter(unsorted()), last);
+        assert_eq!(SortedVec::from_keys(unsorted(), Duplicates::Reject), Err(DuplicateTime(10)));
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut sorted = SortedVec::new();
+        assert_eq!(sorted.insert(20, 'a'), None);
+        assert_eq!(sorted.insert(10, 'b'), None);
+        assert_eq!(sorted.insert(20, 'c'), Some('a'));
+        assert_eq!(sorted.as_slice(), &[(10, 'b'), (20, 'c')]);
+        assert_eq!(sorted.remove(&10), Some('b'));
+        assert_eq!(sorted.remove(&10), None);
+        assert_eq!(sorted.first(), Some((&20, &'c')));
+        sorted.insert(30, 'd');
+        sorted.remove_before(&25);
+        assert_eq!(sorted.as_slice(), &[(30, 'd')]);
+    }
+
+    #[test]
+    fn split() {
+        let sorted = SortedVec::from_iter(vec![(10, 1), (15, 2), (20, 3), (25, 4)]);
+
+        let (mut past, mut future) = sorted.split_at(&17);
+        assert_eq!(past.next(), Some((&15,&2)));
+        assert_eq!(past.next(), Some((&10,&1)));
+        assert_eq!(past.next(), None);
+
+        assert_eq!(future.next(), Some((&20,&3)));
+        assert_eq!(future.next(), Some((&25,&4)));
+        assert_eq!(future.next(), None);
+    }
+
+    #[test]
+    fn timeline() {
+        let timeline = Timeline::<_, StepSampler<_>, _, _>::new(SortedVec::from_iter(unsorted()));
+        assert_eq!(timeline.get_sample(12), Some('e'));
+        assert_eq!(timeline.get_sample(20), Some('c'));
+    }
+}
@@ -0,0 +1,449 @@
+/*!
+ * Import and export of data keys as CSV
+ *
+ * A row is a data key: a time column and one or more value columns, a value can span several columns
+ * (ex: x, y, z columns for a nalgebra Vector3, see [CsvValue](trait.CsvValue.html)).
+ * The fields are separated by a delimiter and trimmed, quoted fields are not supported.
+ * Empty lines are skipped.
+ */
+use crate::interface::TimeLine;
+#[cfg(feature = "nalgebra_impl")]
+use nalgebra::{MatrixMN, DimName, Scalar, allocator::Allocator, DefaultAllocator};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// Values that can be read from and written to CSV columns
+pub trait CsvValue: Sized {
+    /// Number of columns of a value
+    fn columns() -> usize;
+
+    /// Parse a value from exactly `columns()` fields, failing with a readable message
+    fn parse(fields: &[&str]) -> Result<Self, String>;
+
+    /// Append the `columns()` fields of the value
+    fn write(&self, fields: &mut Vec<String>);
+}
+
+macro_rules! impl_csv_scalar {
+    ($t:ty) => {
+        impl CsvValue for $t {
+            fn columns() -> usize {
+                1
+            }
+
+            fn parse(fields: &[&str]) -> Result<Self, String> {
+                fields[0].parse().map_err(|error| format!("{} ({:?})", error, fields[0]))
+            }
+
+            fn write(&self, fields: &mut Vec<String>) {
+                fields.push(self.to_string());
+            }
+        }
+    };
+}
+
+impl_csv_scalar!(f32);
+impl_csv_scalar!(f64);
+impl_csv_scalar!(u8);
+impl_csv_scalar!(i8);
+impl_csv_scalar!(u16);
+impl_csv_scalar!(i16);
+impl_csv_scalar!(u32);
+impl_csv_scalar!(i32);
+impl_csv_scalar!(u64);
+impl_csv_scalar!(i64);
+
+#[cfg(feature = "nalgebra_impl")]
+/// Statically sized nalgebra matrices (and vectors) use one group of columns per element, in column-major order
+impl<N, R, C> CsvValue for MatrixMN<N, R, C>
+where
+    N: Scalar + CsvValue,
+    R: DimName,
+    C: DimName,
+    DefaultAllocator: Allocator<N, R, C> {
+    fn columns() -> usize {
+        R::dim() * C::dim() * N::columns()
+    }
+
+    fn parse(fields: &[&str]) -> Result<Self, String> {
+        let columns = <Self as CsvValue>::columns();
+        if fields.len() != columns {
+            return Err(format!("{} fields for a value of {} columns", fields.len(), columns));
+        }
+        let elements = fields.chunks(N::columns())
+            .map(N::parse)
+            .collect::<Result<Vec<N>, String>>()?;
+        Ok(MatrixMN::from_iterator_generic(R::name(), C::name(), elements))
+    }
+
+    fn write(&self, fields: &mut Vec<String>) {
+        for element in self.iter() {
+            element.write(fields);
+        }
+    }
+}
+
+/// A CSV column, by position (from 0) or by header name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Index(usize),
+    Name(String)
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for Column {
+    fn from(name: &'a str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+/// Reasons why reading CSV data keys failed, lines are counted from 1 (header included)
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    /// A column name is not in the header (or there is no header)
+    UnknownColumn(String),
+    /// The row is too short for the selected columns
+    MissingColumn { line: usize, column: usize },
+    /// A field can't be parsed
+    Parse { line: usize, message: String },
+    /// The row has the same time as a previous row
+    DuplicateTime { line: usize, previous_line: usize },
+    /// The number of value columns does not match the number of columns of a value
+    ColumnCount { expected: usize, found: usize }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::Io(error) => write!(f, "{}", error),
+            CsvError::UnknownColumn(name) => write!(f, "no column named {:?} in the header", name),
+            CsvError::MissingColumn { line, column } => write!(f, "line {}: no column {}", line, column),
+            CsvError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            CsvError::DuplicateTime { line, previous_line } => write!(f, "line {}: same time as line {}", line, previous_line),
+            CsvError::ColumnCount { expected, found } => write!(f, "a value needs {} columns, {} given", expected, found)
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(error: io::Error) -> Self {
+        CsvError::Io(error)
+    }
+}
+
+/// Layout of the CSV data keys
+///
+/// By default the fields are separated by commas, the first line is a header,
+/// the time is the first column and the value is in the following columns
+#[derive(Debug, Clone)]
+pub struct CsvFormat {
+    delimiter: char,
+    header: bool,
+    time_column: Column,
+    value_columns: Vec<Column>
+}
+
+impl CsvFormat {
+    pub fn new() -> Self {
+        CsvFormat {
+            delimiter: ',',
+            header: true,
+            time_column: Column::Index(0),
+            value_columns: Vec::new()
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first line is a header with the names of the columns
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn time_column<C: Into<Column>>(mut self, column: C) -> Self {
+        self.time_column = column.into();
+        self
+    }
+
+    /// Columns of the value, in order (ex: `vec!["x".into(), "y".into()]` for a Vector2),
+    /// when exported they are named after the names given here
+    pub fn value_columns(mut self, columns: Vec<Column>) -> Self {
+        self.value_columns = columns;
+        self
+    }
+
+    /// Read the data keys, sorted by time
+    ///
+    /// Fails on the first row that can't be parsed, or if two rows have the same time
+    pub fn read_keys<T, V, R>(&self, reader: R) -> Result<Vec<(T,V)>, CsvError>
+    where
+        T: FromStr + Ord,
+        T::Err: fmt::Display,
+        V: CsvValue,
+        R: BufRead {
+        let mut rows: Vec<(T, V, usize)> = self.read_rows(reader)?;
+        // stable, so rows at the same time stay in the order of the lines
+        rows.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(CsvError::DuplicateTime { line: pair[1].2, previous_line: pair[0].2 });
+        }
+        Ok(rows.into_iter().map(|(time, value, _)| (time, value)).collect())
+    }
+
+    /// Read the data keys into `timeline` (ex: an empty LerpBtreeTimeline), returning the number of data keys read
+    ///
+    /// Nothing is added to the timeline if reading fails
+    pub fn read_into<T, TL, R>(&self, reader: R, timeline: &mut TL) -> Result<usize, CsvError>
+    where
+        T: FromStr + Ord + Clone,
+        T::Err: fmt::Display,
+        TL: TimeLine<T>,
+        TL::Item: CsvValue,
+        R: BufRead {
+        let keys = self.read_keys(reader)?;
+        let count = keys.len();
+        for (time, value) in keys {
+            timeline.set_key(time, value);
+        }
+        Ok(count)
+    }
+
+    /// Write the data keys (ex: `timeline.iter()` for a timeline on a BTreeMap), a row per key
+    pub fn write_keys<'a, T, V, W, I>(&self, mut writer: W, keys: I) -> io::Result<()>
+    where
+        T: fmt::Display + 'a,
+        V: CsvValue + 'a,
+        W: Write,
+        I: IntoIterator<Item = (&'a T, &'a V)> {
+        self.write_header::<V, W>(&mut writer)?;
+        for (time, value) in keys {
+            let mut fields = vec![time.to_string()];
+            value.write(&mut fields);
+            self.write_row(&mut writer, &fields)?;
+        }
+        Ok(())
+    }
+
+    /// Write the samples of `timeline` at each instant of `times` (ex: a regular grid),
+    /// the value columns of the samples that fail are left empty
+    pub fn write_resampled<T, TL, W, I>(&self, mut writer: W, timeline: &TL, times: I) -> io::Result<()>
+    where
+        T: fmt::Display + Clone,
+        TL: TimeLine<T>,
+        TL::Item: CsvValue,
+        W: Write,
+        I: IntoIterator<Item = T> {
+        self.write_header::<TL::Item, W>(&mut writer)?;
+        for time in times {
+            let mut fields = vec![time.to_string()];
+            match timeline.get_sample(time) {
+                Some(value) => value.write(&mut fields),
+                None => fields.resize(1 + TL::Item::columns(), String::new())
+            }
+            self.write_row(&mut writer, &fields)?;
+        }
+        Ok(())
+    }
+
+    /// Parsed rows, with their line
+    fn read_rows<T, V, R>(&self, reader: R) -> Result<Vec<(T, V, usize)>, CsvError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+        V: CsvValue,
+        R: BufRead {
+        let mut rows = Vec::new();
+        let mut names = Vec::new();
+        let mut columns = None;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if self.header && index == 0 {
+                names = line.split(self.delimiter).map(|name| name.trim().to_string()).collect();
+                // the columns are checked even if there is no row
+                columns = Some(self.resolve::<V>(&names)?);
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (time_index, value_indices) = match &columns {
+                Some(columns) => columns,
+                None => columns.get_or_insert(self.resolve::<V>(&names)?)
+            };
+            let number = index + 1;
+            let fields: Vec<&str> = line.split(self.delimiter).map(str::trim).collect();
+            let field = |column: usize| fields.get(column).cloned()
+                .ok_or(CsvError::MissingColumn { line: number, column });
+            let time = field(*time_index)?;
+            let time = time.parse()
+                .map_err(|error| CsvError::Parse { line: number, message: format!("time {:?}: {}", time, error) })?;
+            let value = value_indices.iter()
+                .map(|column| field(*column))
+                .collect::<Result<Vec<&str>, CsvError>>()?;
+            let value = V::parse(&value).map_err(|message| CsvError::Parse { line: number, message })?;
+            rows.push((time, value, number));
+        }
+        if columns.is_none() {
+            self.resolve::<V>(&names)?;
+        }
+        Ok(rows)
+    }
+
+    /// Indices of the time column and of the value columns
+    fn resolve<V: CsvValue>(&self, names: &[String]) -> Result<(usize, Vec<usize>), CsvError> {
+        let index = |column: &Column| match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => names.iter().position(|n| n == name)
+                .ok_or_else(|| CsvError::UnknownColumn(name.clone()))
+        };
+        let time = index(&self.time_column)?;
+        let values = if self.value_columns.is_empty() {
+            (time + 1..=time + V::columns()).collect()
+        } else {
+            self.value_columns.iter().map(index).collect::<Result<Vec<usize>, CsvError>>()?
+        };
+        if values.len() != V::columns() {
+            return Err(CsvError::ColumnCount { expected: V::columns(), found: values.len() });
+        }
+        Ok((time, values))
+    }
+
+    fn write_header<V: CsvValue, W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if !self.header {
+            return Ok(());
+        }
+        let mut names = vec![match &self.time_column {
+            Column::Name(name) => name.clone(),
+            Column::Index(_) => "time".to_string()
+        }];
+        names.extend((0..V::columns()).map(|index| match self.value_columns.get(index) {
+            Some(Column::Name(name)) => name.clone(),
+            _ if V::columns() == 1 => "value".to_string(),
+            _ => format!("value{}", index)
+        }));
+        self.write_row(writer, &names)
+    }
+
+    fn write_row<W: Write>(&self, writer: &mut W, fields: &[String]) -> io::Result<()> {
+        writeln!(writer, "{}", fields.join(&self.delimiter.to_string()))
+    }
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    const CURVE: &str = "t,height\n10,1.5\n0,0.5\n\n20,4\n";
+
+    #[test]
+    fn load_timeline() {
+        let mut timeline = LerpBtreeTimeline::<u32, f64, f64>::new(BTreeMap::new());
+        assert_eq!(CsvFormat::new().read_into(CURVE.as_bytes(), &mut timeline).unwrap(), 3);
+        assert_eq!(timeline.get_sample(5), Some(1.0));
+        assert_eq!(timeline.get_sample(15), Some(2.75));
+
+        let format = CsvFormat::new().header(false).delimiter(';');
+        let error = format.read_keys::<u32, f32, _>("1.0;5;2\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, CsvError::Parse { line: 1, .. }));
+        let keys: Vec<(u32, f32)> = format.value_columns(vec![2.into()]).read_keys("1;5;2\n0;0;3".as_bytes()).unwrap();
+        assert_eq!(keys, vec![(0, 3.0), (1, 2.0)]);
+    }
+
+    #[test]
+    fn errors() {
+        let format = CsvFormat::new();
+        let read = |csv: &str| format.read_keys::<u32, f64, _>(csv.as_bytes()).unwrap_err();
+        assert!(matches!(read("t,v\n0,1\n1,x\n"), CsvError::Parse { line: 3, .. }));
+        assert!(matches!(read("t,v\n0,1\n1\n"), CsvError::MissingColumn { line: 3, column: 1 }));
+        assert!(matches!(read("t,v\n0,1\n5,2\n0,3\n"), CsvError::DuplicateTime { line: 4, previous_line: 2 }));
+        let error = format.clone().time_column("time").read_keys::<u32, f64, _>(CURVE.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "no column named \"time\" in the header");
+        let error = format.value_columns(vec![1.into(), 2.into()]).read_keys::<u32, f64, _>(CURVE.as_bytes()).unwrap_err();
+        assert!(matches!(error, CsvError::ColumnCount { expected: 1, found: 2 }));
+        // the header is checked without rows, and without lines
+        let format = CsvFormat::new();
+        let error = format.clone().time_column("time").read_keys::<u32, f64, _>("t,height\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, CsvError::UnknownColumn(_)));
+        let error = format.clone().time_column("time").read_keys::<u32, f64, _>("".as_bytes()).unwrap_err();
+        assert!(matches!(error, CsvError::UnknownColumn(_)));
+        assert_eq!(format.read_keys::<u32, f64, _>("t,height\n".as_bytes()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn export() {
+        let timeline = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 0.5), (10, 1.5)].into_iter().collect());
+        let format = CsvFormat::new().time_column("t").value_columns(vec!["height".into()]);
+        let mut csv = Vec::new();
+        format.write_keys(&mut csv, timeline.iter()).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "t,height\n0,0.5\n10,1.5\n");
+
+        let single = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 1.0)].into_iter().collect());
+        let mut csv = Vec::new();
+        CsvFormat::new().write_resampled(&mut csv, &single, vec![0, 5]).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "time,value\n0,\n5,\n");
+        let mut csv = Vec::new();
+        format.write_resampled(&mut csv, &timeline, (0..=20).step_by(5)).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "t,height\n0,0.5\n5,1\n10,1.5\n15,2\n20,2.5\n");
+    }
+}
+
+#[cfg(feature = "nalgebra_impl")]
+#[cfg(test)]
+mod tests_nalgebra {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use nalgebra::Vector2;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn vector_columns() {
+        let csv = "id,x,t,y\na,1,0,2\nb,3,10,6\n";
+        let format = CsvFormat::new().time_column("t").value_columns(vec!["x".into(), "y".into()]);
+        let mut timeline = LerpBtreeTimeline::<u32, Vector2<f64>, f64>::new(BTreeMap::new());
+        format.read_into(csv.as_bytes(), &mut timeline).unwrap();
+        assert_eq!(timeline.get_sample(5), Some(Vector2::new(2.0, 4.0)));
+
+        let mut exported = Vec::new();
+        format.write_keys(&mut exported, timeline.iter()).unwrap();
+        assert_eq!(String::from_utf8(exported).unwrap(), "t,x,y\n0,1,2\n10,3,6\n");
+        let mut exported = Vec::new();
+        CsvFormat::new().write_resampled(&mut exported, &timeline, vec![20]).unwrap();
+        assert_eq!(String::from_utf8(exported).unwrap(), "time,value0,value1\n20,5,10\n");
+    }
+
+    #[test]
+    fn field_count() {
+        assert!(Vector2::<f64>::parse(&["1"]).is_err());
+        assert!(Vector2::<f64>::parse(&["1", "2", "3"]).is_err());
+        assert_eq!(Vector2::<f64>::parse(&["1", "2"]), Ok(Vector2::new(1.0, 2.0)));
+    }
+}
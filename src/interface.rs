@@ -6,32 +6,287 @@
  * [timelines](../timelines/index.html) module use those traits to implement timelines with different
  * datastructures and samplers strategies
  */
+use num_traits::{Float, CheckedAdd, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::ops::{RangeInclusive, Sub};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
 
 
 /// Datastrucutres that can hold data keys
 /// 
 /// The datastructure need to hold the data keys but also give back an iterator of past values
-/// and an iterator of future values for a particular instant of time
+/// and an iterator of future values for a particular instant of time.
+/// The iterators borrow the data keys, so sampling a timeline does not allocate nor clone them
 /// 
 /// T: the type that encode time (ex: u32)
 /// V: the data type that we try to represent (ex: player position)
-pub trait DataContainer<'a,T,V> {
+pub trait DataContainer<T,V> {
+    /// Iterator of past values (most recent first)
+    type Past<'a>: Iterator<Item = (&'a T, &'a V)> where Self: 'a, T: 'a, V: 'a;
+    /// Iterator of future values (oldest first)
+    type Future<'a>: Iterator<Item = (&'a T, &'a V)> where Self: 'a, T: 'a, V: 'a;
+
     /// Split the data collection in half, returning an iterator of past values relative to instant `time` (most recent first)
     /// and an iterator of future values relative to instant `time` (oldest first)
     /// (past, future)
-    fn split_at(&'a self, time: T) -> (Box<Iterator<Item = (T,V)> + 'a>, Box<Iterator<Item = (T,V)> + 'a>);
+    /// A data key at exactly instant `time` is a future value
+    fn split_at<'a>(&'a self, time: &T) -> (Self::Past<'a>, Self::Future<'a>);
+}
+
+/// Datastrucutres whose data keys can be added and removed
+/// 
+/// T: the type that encode time (ex: u32)
+/// V: the data type that we try to represent (ex: player position)
+pub trait DataContainerMut<T,V> {
+    /// Add a data key at instant `time`, returning the data key it replaced if any
+    fn insert_key(&mut self, time: T, value: V) -> Option<V>;
+
+    /// Remove the data key at instant `time` if any
+    fn remove_key(&mut self, time: &T) -> Option<V>;
+
+    /// Remove every data key strictly before instant `time`
+    fn remove_before(&mut self, time: &T);
+}
+
+/// Datastrucutres that can aggregate the data keys in a time range
+///
+/// The default implementation walks the data keys given by `split_at`,
+/// an augmented datastructure (ex: [AggregateVec](../containers/struct.AggregateVec.html)) can answer faster
+pub trait AggregateContainer<T,V>: DataContainer<T,V> {
+    /// Aggregate of the data keys strictly between instants `start` and `end`, joined by `interpolation`
+    /// (None if there is no data key in between)
+    fn aggregate_keys(&self, start: &T, end: &T, interpolation: Interpolation) -> Option<Aggregate<T,V>>
+    where
+        T: Clone + PartialOrd + Sub<Output = T> + ToPrimitive,
+        V: Float {
+        let (_, future) = self.split_at(start);
+        future.skip_while(|(time, _)| *time <= start)
+            .take_while(|(time, _)| *time < end)
+            .map(|(time, value)| Aggregate::point(time.clone(), *value))
+            .reduce(|keys, key| keys.then(key, interpolation))
+    }
+}
+
+/// Datastrucutres that can be serialized as the `keys` of a [Timeline](../timelines/struct.Timeline.html)
+/// (`serde` feature)
+///
+/// The data keys are a list of `[time, value]` sorted by time (ex in JSON: `[[0,1.5],[10,2.5]]`),
+/// a [RingBuffer](../containers/struct.RingBuffer.html) also keeps its capacity: `{"capacity":8,"keys":[[0,1.5],[10,2.5]]}`.
+/// The deserialized data keys do not need to be sorted, but 2 keys at the same instant are an error.
+///
+/// A timeline is a struct of its `keys` and the configuration of its `sampler`
+/// (ex: `{"keys":[[0,1.5],[10,2.5]],"sampler":{"past_extrapolation":"Unbounded","future_extrapolation":{"Limit":5.0}}}`)
+#[cfg(feature = "serde")]
+pub trait SerdeKeys<T,V>: Sized {
+    fn serialize_keys<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where T: Serialize, V: Serialize;
+
+    fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    where T: Deserialize<'de>, V: Deserialize<'de>;
+}
+
+/// Reasons why a sampling algorithme failed to guess a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleError {
+    /// There is no data key at all
+    Empty,
+    /// There is not enough data keys for the sampling strategy (ex: a single key can't be extrapolated)
+    NotEnoughKeys,
+    /// The data keys are not in order (past values must be before `time`, future values at or after `time`)
+    OutOfOrder,
+    /// Two data keys used by the sampling strategy are at the same instant
+    ZeroLengthSegment,
+    /// `time` is further from the data keys than the sampler is configured to extrapolate
+    OutOfRange
+}
+
+impl SampleError {
+    /// Check that `start` is strictly before `end`, so the segment between them can be sampled
+    pub(crate) fn check_segment<T: PartialOrd>(start: &T, end: &T) -> Result<(), SampleError> {
+        match start.partial_cmp(end) {
+            Some(Ordering::Less) => Ok(()),
+            Some(Ordering::Equal) => Err(SampleError::ZeroLengthSegment),
+            _ => Err(SampleError::OutOfOrder)
+        }
+    }
+
+    /// Check that `start` is before or at `end`
+    pub(crate) fn check_order<T: PartialOrd>(start: &T, end: &T) -> Result<(), SampleError> {
+        match start.partial_cmp(end) {
+            Some(Ordering::Less) | Some(Ordering::Equal) => Ok(()),
+            _ => Err(SampleError::OutOfOrder)
+        }
+    }
 }
 
+impl fmt::Display for SampleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SampleError::Empty => write!(f, "no data key to sample"),
+            SampleError::NotEnoughKeys => write!(f, "not enough data keys to sample"),
+            SampleError::OutOfOrder => write!(f, "data keys are out of order"),
+            SampleError::ZeroLengthSegment => write!(f, "two data keys are at the same instant"),
+            SampleError::OutOfRange => write!(f, "too far from the data keys to extrapolate")
+        }
+    }
+}
+
+impl Error for SampleError {}
+
 /// Represents a strategy used to inter/extrapolate values
 /// ex: Linear
+/// The sampler instance holds the configuration of the strategy (ex: extrapolation limits)
 /// T: the type that encode time (ex: u32)
 /// V: the data type that we try to represent (ex: player position)
 pub trait DataSampler<T,V> {
     /// take all the past and future data keys relative to time to feed to the sampling algorithme
-    /// if not enough data keys are available (or if they are not in order), the sampling algorithme can fail by returning None
-    fn sample(past_values: &mut Iterator<Item = (T,V)>, future_values: &mut Iterator<Item = (T,V)>, time: T) -> Option<V>;
+    /// if not enough data keys are available (or if they are not in order), the sampling algorithme fails
+    /// with the corresponding [SampleError](enum.SampleError.html)
+    fn try_sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<V, SampleError>
+    where T: 'a, V: 'a;
+
+    /// Same as `try_sample` but returns None on failure
+    fn sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Option<V>
+    where T: 'a, V: 'a {
+        self.try_sample(past_values, future_values, time).ok()
+    }
+}
+
+/// A sampling strategy that can also guess the rate of change of the value
+/// 
+/// The derivatives are in value units per time unit, following the same curve as `try_sample`
+/// (ex: the slope of the segment for a linear strategy)
+pub trait DerivativeSampler<T,V>: DataSampler<T,V> {
+    /// Type of the rate of change (ex: velocity for a position)
+    type Derivative;
+
+    /// Guess the first derivative at instant `time` (ex: velocity for a position)
+    fn try_sample_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a;
+
+    /// Guess the second derivative at instant `time` (ex: acceleration for a position)
+    fn try_sample_second_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a;
+}
+
+
+/// How a sampling strategy goes from a data key to the next one,
+/// so the aggregates of a timeline can be computed from the data keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// The value of a data key is held until the next one
+    Step,
+    /// The value goes linearly from a data key to the next one
+    Linear
+}
+
+impl Interpolation {
+    /// Integral of the value from point `start` to point `end`
+    pub fn integral<T,V>(&self, (start_time, start_value): (&T, &V), (end_time, end_value): (&T, &V)) -> V
+    where
+        T: Clone + Sub<Output = T> + ToPrimitive,
+        V: Float {
+        let duration: V = duration(start_time, end_time);
+        match self {
+            Interpolation::Step => *start_value * duration,
+            Interpolation::Linear => (*start_value + *end_value) * duration / (V::one() + V::one())
+        }
+    }
+}
+
+/// Duration from `start` to `end` converted to the float type of the values,
+/// so the time type does not need to convert into the value type (ex: u64 time and f32 values).
+/// NaN if the float type can't represent it
+pub(crate) fn duration<T,V>(start: &T, end: &T) -> V
+where
+    T: Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    V::from(end.clone() - start.clone()).unwrap_or_else(V::nan)
+}
+
+/// A sampling strategy whose curve between the data keys is known, so the aggregates
+/// and the threshold crossings of a timeline can be computed exactly from the data keys
+pub trait AggregateSampler<T,V>: DataSampler<T,V> {
+    fn interpolation(&self) -> Interpolation;
+
+    /// Distance from the nearest data key at which the extrapolated curve makes `ratio` (0 to 1) of its change
+    /// from distance `from` to distance `to`, before the first data key if `past` (time units converted to F)
+    ///
+    /// The default is for a linear extrapolation
+    fn extrapolated_distance<F: Float>(&self, past: bool, (from, to): (F, F), ratio: F) -> F {
+        let _ = past;
+        from + (to - from) * ratio
+    }
+
+    /// Mean over the distances `from` to `to` of the ratio (0 to 1) of the change made by the extrapolated curve
+    /// from distance `from` to distance `to`, before the first data key if `past` (time units converted to F)
+    ///
+    /// The integral of the extrapolation is the duration multiplied by `v_from + (v_to - v_from) * mean`.
+    /// The default is for a linear extrapolation
+    fn extrapolated_mean<F: Float>(&self, past: bool, (from, to): (F, F)) -> F {
+        let _ = (past, from, to);
+        F::one() / (F::one() + F::one())
+    }
 }
 
+/// Aggregates of a scalar curve from a first point to a last point (ex: the data keys in a time range)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate<T,V> {
+    pub first: (T,V),
+    pub last: (T,V),
+    pub min: V,
+    pub max: V,
+    /// Integral of the value over time, in value units multiplied by time units
+    pub integral: V
+}
+
+impl<T,V> Aggregate<T,V>
+where
+    T: Clone + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    /// Aggregate of a single point
+    pub fn point(time: T, value: V) -> Self {
+        Aggregate {
+            first: (time.clone(), value),
+            last: (time, value),
+            min: value,
+            max: value,
+            integral: V::zero()
+        }
+    }
+
+    /// Aggregate of this curve followed by the `next` one, joined by `interpolation`
+    pub fn then(self, next: Aggregate<T,V>, interpolation: Interpolation) -> Self {
+        let join = interpolation.integral((&self.last.0, &self.last.1), (&next.first.0, &next.first.1));
+        self.joined(next, join)
+    }
+
+    /// Aggregate of this curve followed by the `next` one, with the integral `join` of the curve between them
+    ///
+    /// The curve between them must stay between their values (ex: a linear or an extrapolated segment)
+    pub fn joined(self, next: Aggregate<T,V>, join: V) -> Self {
+        Aggregate {
+            first: self.first,
+            last: next.last,
+            min: self.min.min(next.min),
+            max: self.max.max(next.max),
+            integral: self.integral + join + next.integral
+        }
+    }
+
+    /// Time-weighted mean of the value, the first value if the curve is a single instant
+    pub fn mean(&self) -> V {
+        let duration: V = duration(&self.first.0, &self.last.0);
+        if duration == V::zero() {
+            self.first.1
+        } else {
+            self.integral / duration
+        }
+    }
+}
 
 /// A Timeline that represent the evolution of a value across time
 /// 
@@ -46,8 +301,13 @@ pub trait TimeLine<T: Clone> {
     type Item: Clone;
     /// Try to guess a value for a particular instant 
     /// The strategy used to inter/extrapolate depend on the implementation 
-    /// and can fail by returning None
-    fn get_sample(&self, time: T) -> Option<Self::Item>;
+    /// and can fail with the reason of the failure
+    fn try_get_sample(&self, time: T) -> Result<Self::Item, SampleError>;
+
+    /// Same as `try_get_sample` but returns None on failure
+    fn get_sample(&self, time: T) -> Option<Self::Item> {
+        self.try_get_sample(time).ok()
+    }
 
     /// Add a new data key at a given time.
     /// WARNING depending on the sample strategy used, this action may change timeline value around this instant
@@ -63,23 +323,114 @@ pub trait TimeLine<T: Clone> {
     /// `correction_time_segment.1` is the instant at which the correction should end (ex: 200 ms)
     /// 
     /// This method can be used to imlement dead reckoning with smooth correction
-    /// The smooth correction might fail due to sampling fail, in this case the timeline is cleaned up before returning None
-    fn smooth_correct(&mut self, correction: (T, Self::Item), correction_time_segment: (T,T)) -> Option<Self::Item> {
-        let start_value = self.get_sample(correction_time_segment.0.clone())?;
+    /// The smooth correction might fail due to sampling fail, in this case the timeline is cleaned up before returning the error
+    fn try_smooth_correct(&mut self, correction: (T, Self::Item), correction_time_segment: (T,T)) -> Result<Self::Item, SampleError> {
+        let start_value = self.try_get_sample(correction_time_segment.0.clone())?;
         self.set_key(correction.0.clone(), correction.1.clone());
-        match self.get_sample(correction_time_segment.1.clone()) {
-            Some(end_value) => {
+        match self.try_get_sample(correction_time_segment.1.clone()) {
+            Ok(end_value) => {
                 self.set_key(correction_time_segment.0, start_value);
                 self.set_key(correction_time_segment.1.clone(), end_value.clone());
-                Some(end_value.clone())
+                Ok(end_value)
             }
-            None => {
+            Err(error) => {
                 self.remove_key(correction.0);
-                None
+                Err(error)
             }
         }
     }
+
+    /// Same as `try_smooth_correct` but returns None on failure
+    fn smooth_correct(&mut self, correction: (T, Self::Item), correction_time_segment: (T,T)) -> Option<Self::Item> {
+        self.try_smooth_correct(correction, correction_time_segment).ok()
+    }
+
+    /// Iterator of the samples every `step` in `range` (ex: plotting, fixed step physics),
+    /// yielding `(time, sample)` with the reason of the failure for the samples that fail
+    ///
+    /// Each instant is sampled with `try_get_sample`.
+    /// [Timeline](../timelines/struct.Timeline.html) has `resample_keys` walking the data keys once instead.
+    /// Yields nothing if `step` is not positive, and stops before the time overflows
+    fn resample(&self, range: RangeInclusive<T>, step: T) -> Samples<'_, Self, T>
+    where
+        Self: Sized,
+        T: Ord + CheckedAdd + Zero {
+        Samples {
+            timeline: self,
+            instants: Instants::new(range, step)
+        }
+    }
+}
+
+/// Regular instants of a range, from the start up to the end included
+#[derive(Debug, Clone)]
+pub(crate) struct Instants<T> {
+    next: Option<T>,
+    end: T,
+    step: T
+}
+
+impl<T: Clone + Ord + CheckedAdd + Zero> Instants<T> {
+    /// Empty if `step` is not positive or if the range is reversed
+    pub(crate) fn new(range: RangeInclusive<T>, step: T) -> Self {
+        let (start, end) = range.into_inner();
+        Instants {
+            next: Some(start).filter(|start| step > T::zero() && *start <= end),
+            end,
+            step
+        }
+    }
 }
 
+impl<T: Clone + Ord + CheckedAdd + Zero> Iterator for Instants<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let time = self.next.take()?;
+        self.next = time.checked_add(&self.step).filter(|next| *next <= self.end);
+        Some(time)
+    }
+}
 
+/// Iterator of the samples of a [TimeLine](trait.TimeLine.html) at regular instants,
+/// built by [resample](trait.TimeLine.html#method.resample)
+pub struct Samples<'a, TL, T> {
+    timeline: &'a TL,
+    instants: Instants<T>
+}
 
+impl<'a, TL, T> Iterator for Samples<'a, TL, T>
+where
+    TL: TimeLine<T>,
+    T: Clone + Ord + CheckedAdd + Zero {
+    type Item = (T, Result<TL::Item, SampleError>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.instants.next()?;
+        Some((time.clone(), self.timeline.try_get_sample(time)))
+    }
+}
+
+/// A Timeline that can also guess the rate of change of its value
+/// 
+/// The derivatives are in value units per time unit
+/// 
+/// T: the type that encode time (ex: u32)
+pub trait DerivativeTimeLine<T: Clone>: TimeLine<T> {
+    /// Type of the rate of change (ex: velocity for a position)
+    type Derivative;
+
+    /// Try to guess the first derivative for a particular instant (ex: velocity for a position)
+    fn try_get_derivative(&self, time: T) -> Result<Self::Derivative, SampleError>;
+
+    /// Same as `try_get_derivative` but returns None on failure
+    fn get_derivative(&self, time: T) -> Option<Self::Derivative> {
+        self.try_get_derivative(time).ok()
+    }
+
+    /// Try to guess the second derivative for a particular instant (ex: acceleration for a position)
+    fn try_get_second_derivative(&self, time: T) -> Result<Self::Derivative, SampleError>;
+
+    /// Same as `try_get_second_derivative` but returns None on failure
+    fn get_second_derivative(&self, time: T) -> Option<Self::Derivative> {
+        self.try_get_second_derivative(time).ok()
+    }
+}
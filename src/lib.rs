@@ -7,16 +7,24 @@
  * 
  * Different *polation strategies can be used to guess the value of the data.
  * [sampler](samplers/index.html) contains different interpolation strategies
+ *
+ * With the `serde` feature, timelines, containers and samplers can be serialized,
+ * see [SerdeKeys](interface/trait.SerdeKeys.html) for the representation.
+ * Data keys can also be imported and exported as CSV with [csv](csv/index.html)
  */
 #[cfg(feature = "nalgebra_impl")]
 extern crate nalgebra;
 extern crate num_traits;
+#[cfg(feature = "serde")]
+extern crate serde;
 //extern crate alga;
 
 pub mod interface;
 pub mod samplers;
 pub mod containers;
 pub mod timelines;
+pub mod network;
+pub mod csv;
 
 
 #[cfg(test)]
@@ -0,0 +1,275 @@
+use crate::interface::{TimeLine, DerivativeTimeLine, SampleError};
+use num_traits::{Float, NumCast};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use super::to_float;
+
+/// Estimate of the clock of a remote peer (ex: the server), from ping/pong exchanges
+///
+/// Each exchange gives 3 instants: the local instant the ping was sent, the remote instant the peer answered
+/// and the local instant the pong was received. Assuming the network is symmetric, the remote clock
+/// was `remote` at the middle of the round trip, which gives the offset between the clocks.
+///
+/// The exchanges much slower than the fastest recent ones (more than `outlier_factor` times the shortest round trip
+/// of the last `history` exchanges) are rejected, the offset they give is spoiled by network congestion.
+/// The accepted offsets are stored in a timeline keyed by local time, so the offset can drift:
+/// with a [LerpSampler](../samplers/struct.LerpSampler.html) the drift is the derivative of the offset timeline.
+/// The timeline keeps every accepted offset, wrap it in [Retention](../timelines/struct.Retention.html) to forget the old ones.
+///
+/// The rejected round trips still count in the last `history` exchanges: if the route to the peer gets slower for good,
+/// the short round trips of the old route leave the history and the new ones are accepted again.
+///
+/// TL: the timeline of the offsets (ex: LerpBtreeTimeline<i32, f64, f64>)
+/// T: the type that encode time, for both local and remote instants (ex: i32 milliseconds)
+/// F: the float type of the offsets, in T units
+pub struct ClockSync<TL, T, F = f64> {
+    offsets: TL,
+    history: usize,
+    outlier_factor: F,
+    smoothing: F,
+    round_trips: VecDeque<F>,
+    latest: Option<F>,
+    accepted: usize,
+    rejected: usize,
+    _phantom: PhantomData<T>
+}
+
+impl<TL, T, F> ClockSync<TL, T, F>
+where
+    TL: TimeLine<T, Item = F>,
+    T: Copy + NumCast,
+    F: Float {
+    /// Clock estimate rejecting the round trips 1.5 times longer than the shortest of the last 16 exchanges,
+    /// a new offset is averaged half and half with the estimated one
+    pub fn new(offsets: TL) -> Self {
+        let half = F::one() / (F::one() + F::one());
+        ClockSync {
+            offsets,
+            history: 16,
+            outlier_factor: F::one() + half,
+            smoothing: half,
+            round_trips: VecDeque::new(),
+            latest: None,
+            accepted: 0,
+            rejected: 0,
+            _phantom: PhantomData
+        }
+    }
+
+    pub fn builder(offsets: TL) -> ClockSyncBuilder<TL, T, F> {
+        ClockSyncBuilder {
+            sync: ClockSync::new(offsets)
+        }
+    }
+
+    /// Add a ping/pong exchange, returning false if it was rejected as an outlier
+    ///
+    /// `send` and `receive` are local instants, `remote` is the instant of the remote peer when it answered
+    pub fn add_sample(&mut self, send: T, remote: T, receive: T) -> bool {
+        let (send, remote, receive): (F, F, F) = (to_float(send), to_float(remote), to_float(receive));
+        let round_trip = receive - send;
+        if round_trip < F::zero() {
+            self.rejected += 1;
+            return false;
+        }
+        let shortest = self.round_trip();
+        // outliers are recorded too, so a lasting slowdown of the network ends up as the new reference
+        if self.round_trips.len() >= self.history {
+            self.round_trips.pop_front();
+        }
+        self.round_trips.push_back(round_trip);
+
+        let middle = send + round_trip / (F::one() + F::one());
+        let local = match T::from(middle) {
+            Some(local) => local,
+            None => {
+                self.rejected += 1;
+                return false;
+            }
+        };
+        match shortest {
+            Some(shortest) if round_trip > shortest * self.outlier_factor => {
+                self.rejected += 1;
+                false
+            }
+            _ => {
+                let offset = remote - middle;
+                // a single stored offset can't be sampled by every sampler, `offset` falls back to the latest one
+                let offset = match self.offset(local) {
+                    Ok(estimate) => estimate + (offset - estimate) * self.smoothing,
+                    Err(_) => offset
+                };
+                self.offsets.set_key(local, offset);
+                self.latest = Some(offset);
+                self.accepted += 1;
+                true
+            }
+        }
+    }
+
+    /// Difference between the remote and the local clock at local instant `local`
+    ///
+    /// With a single accepted exchange the offset is constant
+    pub fn offset(&self, local: T) -> Result<F, SampleError> {
+        match self.offsets.try_get_sample(local) {
+            Err(SampleError::NotEnoughKeys) => self.latest.ok_or(SampleError::NotEnoughKeys),
+            result => result
+        }
+    }
+
+    /// Remote instant at local instant `local`
+    pub fn remote_time(&self, local: T) -> Result<T, SampleError> {
+        let remote = to_float::<T,F>(local) + self.offset(local)?;
+        T::from(remote).ok_or(SampleError::OutOfRange)
+    }
+
+    /// Local instant at remote instant `remote`
+    pub fn local_time(&self, remote: T) -> Result<T, SampleError> {
+        let remote_float: F = to_float(remote);
+        // the offset is first estimated at the remote instant as if it was local, then at the found instant
+        let guess = T::from(remote_float - self.offset(remote)?).ok_or(SampleError::OutOfRange)?;
+        T::from(remote_float - self.offset(guess)?).ok_or(SampleError::OutOfRange)
+    }
+
+    /// How fast the remote clock goes compared to the local one, minus 1 (ex: 0.001 when it gains 1 ms per second)
+    pub fn drift(&self, local: T) -> Result<F, SampleError>
+    where TL: DerivativeTimeLine<T, Derivative = F> {
+        match self.offsets.try_get_derivative(local) {
+            Err(SampleError::NotEnoughKeys) if self.latest.is_some() => Ok(F::zero()),
+            result => result
+        }
+    }
+
+    /// Shortest round trip of the last exchanges
+    pub fn round_trip(&self) -> Option<F> {
+        self.round_trips.iter().cloned().fold(None, |shortest: Option<F>, rtt| {
+            Some(shortest.map_or(rtt, |shortest| shortest.min(rtt)))
+        })
+    }
+
+    /// Number of exchanges used to estimate the offset
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Number of exchanges rejected as outliers
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Timeline of the offsets, keyed by local time
+    pub fn offsets(&self) -> &TL {
+        &self.offsets
+    }
+
+    pub fn into_inner(self) -> TL {
+        self.offsets
+    }
+}
+
+/// Builder of a configured [ClockSync](struct.ClockSync.html)
+pub struct ClockSyncBuilder<TL, T, F> {
+    sync: ClockSync<TL, T, F>
+}
+
+impl<TL, T, F> ClockSyncBuilder<TL, T, F>
+where
+    TL: TimeLine<T, Item = F>,
+    T: Copy + NumCast,
+    F: Float {
+    /// Number of recent exchanges whose shortest round trip is the reference to reject outliers
+    ///
+    /// Panics if `exchanges` is 0
+    pub fn history(mut self, exchanges: usize) -> Self {
+        assert!(exchanges > 0, "the clock needs to remember at least one exchange");
+        self.sync.history = exchanges;
+        self
+    }
+
+    /// How many times longer than the shortest recent round trip an exchange can be before it is rejected
+    pub fn outlier_factor(mut self, factor: F) -> Self {
+        self.sync.outlier_factor = factor;
+        self
+    }
+
+    /// Weight of a new offset against the estimated one, between 0 and 1 (1 trusts every exchange)
+    pub fn smoothing(mut self, weight: F) -> Self {
+        self.sync.smoothing = weight;
+        self
+    }
+
+    pub fn build(self) -> ClockSync<TL, T, F> {
+        self.sync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    type Clock = ClockSync<LerpBtreeTimeline<i32, f64, f64>, i32>;
+
+    #[test]
+    fn constant_offset() {
+        let mut sync: Clock = ClockSync::new(LerpBtreeTimeline::new(BTreeMap::new()));
+        assert_eq!(sync.remote_time(0), Err(SampleError::Empty));
+        // remote clock is 1000 ahead, 10 each way
+        assert!(sync.add_sample(0, 1010, 20));
+        assert_eq!(sync.offset(10), Ok(1000.0));
+        assert_eq!(sync.drift(10), Ok(0.0));
+        assert_eq!(sync.remote_time(500), Ok(1500));
+        assert_eq!(sync.local_time(1500), Ok(500));
+        // congested exchange, the answer looks 90 late
+        assert!(!sync.add_sample(100, 1300, 300));
+        assert!(sync.add_sample(1000, 2015, 1030));
+        assert_eq!((sync.accepted(), sync.rejected()), (2, 1));
+        assert_eq!(sync.round_trip(), Some(20.0));
+        assert_eq!(sync.offset(5000), Ok(1000.0));
+    }
+
+    #[test]
+    fn smoothing_and_retention() {
+        use crate::timelines::Retention;
+        let offsets = Retention::new(LerpBtreeTimeline::<i32, f64, f64>::new(BTreeMap::new()), 5000);
+        let mut sync = ClockSync::new(offsets);
+        assert!(sync.add_sample(0, 1010, 20));
+        // the second offset is averaged with the first one, even if the timeline can't interpolate a single key
+        assert!(sync.add_sample(1000, 2020, 1020));
+        assert_eq!(sync.offset(1010), Ok(1005.0));
+        for i in 2..20 {
+            sync.add_sample(i * 1000, i * 1000 + 1010, i * 1000 + 20);
+        }
+        // the 6 offsets of the last 5000, and the one before to interpolate at the edge
+        assert_eq!(sync.offsets().inner().len(), 7);
+    }
+
+    #[test]
+    fn slower_network() {
+        let mut sync: Clock = ClockSync::builder(LerpBtreeTimeline::new(BTreeMap::new()))
+            .history(4)
+            .build();
+        assert!(sync.add_sample(0, 1010, 20));
+        // the round trip is now 100 for good, rejected until the 20 leaves the last 4 exchanges
+        let accepted: Vec<bool> = (1..=5).map(|i| sync.add_sample(i * 1000, i * 1000 + 1050, i * 1000 + 100)).collect();
+        assert_eq!(accepted, vec![false, false, false, false, true]);
+        assert_eq!(sync.round_trip(), Some(100.0));
+    }
+
+    #[test]
+    fn drifting_clock() {
+        let mut sync: Clock = ClockSync::builder(LerpBtreeTimeline::new(BTreeMap::new()))
+            .smoothing(1.0)
+            .build();
+        // remote clock gains 1 every 1000
+        for i in 0..10 {
+            let send = i * 1000;
+            let middle = send + 5;
+            assert!(sync.add_sample(send, middle + 500 + middle / 1000, send + 10));
+        }
+        assert!((sync.drift(20_000).unwrap() - 0.001).abs() < 1e-9);
+        assert_eq!(sync.remote_time(20_005), Ok(20_525));
+        assert_eq!(sync.local_time(20_525), Ok(20_005));
+    }
+}
@@ -0,0 +1,350 @@
+use crate::interface::{DataContainer, TimeLine};
+#[cfg(feature = "nalgebra_impl")]
+use nalgebra::{MatrixMN, DimName, Scalar, allocator::Allocator, DefaultAllocator};
+use std::error::Error;
+use std::fmt;
+
+/// Compact binary encoding of data keys, to send them over the network
+///
+/// The format is the number of keys (varint) followed by the keys sorted by time,
+/// each key is its time then its value, both encoded relative to the previous key with [DeltaEncode](trait.DeltaEncode.html):
+/// integers are the difference with the previous key as a zigzag varint (1 byte for a difference between -64 and 63),
+/// floats are raw unless the codec quantizes them.
+///
+/// The first key is encoded relative to nothing (as if the previous key was 0), so a message can be decoded on its own.
+pub struct KeyCodec {
+    quantum: Option<f64>
+}
+
+impl KeyCodec {
+    /// Lossless codec, floats are encoded raw
+    pub fn new() -> Self {
+        KeyCodec {
+            quantum: None
+        }
+    }
+
+    /// Codec rounding floats to a multiple of `quantum` (ex: 0.01 for centimeters with positions in meters),
+    /// the floats are then encoded as integers relative to the previous key
+    pub fn quantized(quantum: f64) -> Self {
+        KeyCodec {
+            quantum: Some(quantum)
+        }
+    }
+
+    pub fn quantum(&self) -> Option<f64> {
+        self.quantum
+    }
+
+    /// Append the data keys to `buffer`, they must be sorted by time (ex: a timeline split)
+    pub fn encode<'a, T, V, I>(&self, keys: I, buffer: &mut Vec<u8>)
+    where
+        T: DeltaEncode + 'a,
+        V: DeltaEncode + 'a,
+        I: IntoIterator<Item = (&'a T, &'a V)> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        write_varint(keys.len() as u64, buffer);
+        let mut previous: Option<(&T, &V)> = None;
+        for (time, value) in keys {
+            time.encode_delta(previous.map(|(time, _)| time), None, buffer);
+            value.encode_delta(previous.map(|(_, value)| value), self.quantum, buffer);
+            previous = Some((time, value));
+        }
+    }
+
+    /// Append the data keys of `container` strictly after instant `acknowledged` to `buffer`
+    /// (ex: the keys the peer did not acknowledge yet)
+    pub fn encode_since<T, V, C>(&self, container: &C, acknowledged: &T, buffer: &mut Vec<u8>)
+    where
+        T: DeltaEncode + PartialEq,
+        V: DeltaEncode,
+        C: DataContainer<T,V> {
+        let (_, future) = container.split_at(acknowledged);
+        self.encode(future.skip_while(|(time, _)| *time == acknowledged), buffer);
+    }
+
+    /// Read back data keys encoded by `encode`
+    pub fn decode<T, V>(&self, mut bytes: &[u8]) -> Result<Vec<(T,V)>, DecodeError>
+    where
+        T: DeltaEncode,
+        V: DeltaEncode {
+        let count = read_varint(&mut bytes)?;
+        let mut keys: Vec<(T,V)> = Vec::new();
+        for _ in 0..count {
+            let previous = keys.last();
+            let time = T::decode_delta(previous.map(|(time, _)| time), None, &mut bytes)?;
+            let value = V::decode_delta(previous.map(|(_, value)| value), self.quantum, &mut bytes)?;
+            keys.push((time, value));
+        }
+        if bytes.is_empty() {
+            Ok(keys)
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+
+    /// Decode data keys and add them to `timeline`, returning the number of keys added
+    ///
+    /// Nothing is added if the decoding fails
+    pub fn decode_into<T, TL>(&self, bytes: &[u8], timeline: &mut TL) -> Result<usize, DecodeError>
+    where
+        T: DeltaEncode + Clone,
+        TL: TimeLine<T>,
+        TL::Item: DeltaEncode {
+        let keys = self.decode(bytes)?;
+        let count = keys.len();
+        for (time, value) in keys {
+            timeline.set_key(time, value);
+        }
+        Ok(count)
+    }
+}
+
+impl Default for KeyCodec {
+    fn default() -> Self {
+        KeyCodec::new()
+    }
+}
+
+/// Reasons why decoding data keys failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The message ends in the middle of a key
+    UnexpectedEnd,
+    /// A varint is longer than 64 bits
+    VarintOverflow,
+    /// There is data after the last key
+    TrailingBytes
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "the message ends in the middle of a data key"),
+            DecodeError::VarintOverflow => write!(f, "a varint is longer than 64 bits"),
+            DecodeError::TrailingBytes => write!(f, "there is data after the last data key")
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Items that can be encoded relative to the previous one by [KeyCodec](struct.KeyCodec.html)
+pub trait DeltaEncode: Sized {
+    /// Append self to `buffer`, relative to `previous` if any,
+    /// floats are rounded to a multiple of `quantum` if any
+    fn encode_delta(&self, previous: Option<&Self>, quantum: Option<f64>, buffer: &mut Vec<u8>);
+
+    /// Read an item relative to `previous` from the start of `bytes`, advancing `bytes`
+    fn decode_delta(previous: Option<&Self>, quantum: Option<f64>, bytes: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        *bytes = rest;
+        // the 10th byte only has room for the last bit of a u64
+        if shift == 63 && byte & 0x7f > 1 {
+            return Err(DecodeError::VarintOverflow);
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::VarintOverflow)
+}
+
+/// Small negative numbers are mapped to small unsigned ones: 0, -1, 1, -2... -> 0, 1, 2, 3...
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+macro_rules! impl_delta_integer {
+    ($t:ty, $s:ty) => {
+        impl DeltaEncode for $t {
+            fn encode_delta(&self, previous: Option<&Self>, _quantum: Option<f64>, buffer: &mut Vec<u8>) {
+                let delta = self.wrapping_sub(*previous.unwrap_or(&0));
+                write_varint(zigzag(delta as $s as i64), buffer);
+            }
+
+            fn decode_delta(previous: Option<&Self>, _quantum: Option<f64>, bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+                let delta = unzigzag(read_varint(bytes)?) as $s as $t;
+                Ok(previous.unwrap_or(&0).wrapping_add(delta))
+            }
+        }
+    };
+}
+
+impl_delta_integer!(u8, i8);
+impl_delta_integer!(i8, i8);
+impl_delta_integer!(u16, i16);
+impl_delta_integer!(i16, i16);
+impl_delta_integer!(u32, i32);
+impl_delta_integer!(i32, i32);
+impl_delta_integer!(u64, i64);
+impl_delta_integer!(i64, i64);
+impl_delta_integer!(usize, isize);
+impl_delta_integer!(isize, isize);
+
+macro_rules! impl_delta_float {
+    ($t:ty, $n:expr) => {
+        /// Raw little endian bytes, or the difference of the quantized values as a zigzag varint
+        impl DeltaEncode for $t {
+            fn encode_delta(&self, previous: Option<&Self>, quantum: Option<f64>, buffer: &mut Vec<u8>) {
+                match quantum {
+                    Some(quantum) => {
+                        let quantize = |value: $t| (f64::from(value) / quantum).round() as i64;
+                        let delta = quantize(*self).wrapping_sub(previous.map_or(0, |previous| quantize(*previous)));
+                        write_varint(zigzag(delta), buffer);
+                    }
+                    None => buffer.extend_from_slice(&self.to_le_bytes())
+                }
+            }
+
+            fn decode_delta(previous: Option<&Self>, quantum: Option<f64>, bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+                match quantum {
+                    Some(quantum) => {
+                        let previous = previous.map_or(0, |previous| (f64::from(*previous) / quantum).round() as i64);
+                        let quantized = previous.wrapping_add(unzigzag(read_varint(bytes)?));
+                        Ok((quantized as f64 * quantum) as $t)
+                    }
+                    None => {
+                        if bytes.len() < $n {
+                            return Err(DecodeError::UnexpectedEnd);
+                        }
+                        let (raw, rest) = bytes.split_at($n);
+                        *bytes = rest;
+                        let mut array = [0; $n];
+                        array.copy_from_slice(raw);
+                        Ok(<$t>::from_le_bytes(array))
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_delta_float!(f32, 4);
+impl_delta_float!(f64, 8);
+
+#[cfg(feature = "nalgebra_impl")]
+/// Memberwise encoding of statically sized nalgebra matrices (and vectors)
+impl<N, R, C> DeltaEncode for MatrixMN<N, R, C>
+where
+    N: Scalar + DeltaEncode,
+    R: DimName,
+    C: DimName,
+    DefaultAllocator: Allocator<N, R, C> {
+    fn encode_delta(&self, previous: Option<&Self>, quantum: Option<f64>, buffer: &mut Vec<u8>) {
+        for (index, element) in self.iter().enumerate() {
+            element.encode_delta(previous.map(|previous| &previous[index]), quantum, buffer);
+        }
+    }
+
+    fn decode_delta(previous: Option<&Self>, quantum: Option<f64>, bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        let elements = (0..R::dim() * C::dim())
+            .map(|index| N::decode_delta(previous.map(|previous| &previous[index]), quantum, bytes))
+            .collect::<Result<Vec<N>, DecodeError>>()?;
+        Ok(MatrixMN::from_iterator_generic(R::name(), C::name(), elements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+    use std::iter::FromIterator;
+
+    #[test]
+    fn varints() {
+        let mut buffer = Vec::new();
+        for value in &[0, 1, 127, 128, 300, u64::MAX] {
+            write_varint(*value, &mut buffer);
+        }
+        assert_eq!(buffer.len(), 1 + 1 + 1 + 2 + 2 + 10);
+        let mut bytes = &buffer[..];
+        for value in &[0, 1, 127, 128, 300, u64::MAX] {
+            assert_eq!(read_varint(&mut bytes), Ok(*value));
+        }
+        assert_eq!(read_varint(&mut bytes), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(read_varint(&mut &[0xff; 11][..]), Err(DecodeError::VarintOverflow));
+        // 10 bytes, the last one has more than the 64th bit
+        let mut overlong = [0xff; 10];
+        overlong[9] = 0x02;
+        assert_eq!(read_varint(&mut &overlong[..]), Err(DecodeError::VarintOverflow));
+        overlong[9] = 0x01;
+        assert_eq!(read_varint(&mut &overlong[..]), Ok(u64::MAX));
+        for value in &[0, -1, 1, -64, 63, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(*value)), *value);
+        }
+        assert_eq!(zigzag(-64), 127);
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let keys = BTreeMap::from_iter(vec![(1000_u32, 1.5_f64), (1016, -2.25), (1033, 1e300)]);
+        let codec = KeyCodec::new();
+        let mut buffer = Vec::new();
+        codec.encode(&keys, &mut buffer);
+        // count, first time (2 bytes) and 2 deltas, 3 raw floats
+        assert_eq!(buffer.len(), 1 + 2 + 1 + 1 + 3 * 8);
+        assert_eq!(codec.decode(&buffer), Ok(keys.into_iter().collect::<Vec<_>>()));
+        assert_eq!(codec.decode::<u32, f64>(&buffer[..buffer.len() - 1]), Err(DecodeError::UnexpectedEnd));
+        buffer.push(0);
+        assert_eq!(codec.decode::<u32, f64>(&buffer), Err(DecodeError::TrailingBytes));
+    }
+
+    #[test]
+    fn quantized_since_acknowledged() {
+        let mut timeline = LerpBtreeTimeline::<i32, f64, f64>::new(BTreeMap::new());
+        for t in 0..10 {
+            timeline.set_key(t * 16, 100.0 + f64::from(t) * 0.123);
+        }
+        let codec = KeyCodec::quantized(0.01);
+        let mut buffer = Vec::new();
+        codec.encode_since(&*timeline, &64, &mut buffer);
+        let keys: Vec<(i32, f64)> = codec.decode(&buffer).unwrap();
+        assert_eq!(keys.iter().map(|(t, _)| *t).collect::<Vec<_>>(), vec![80, 96, 112, 128, 144]);
+        for (t, value) in keys {
+            assert!((value - timeline.get(&t).unwrap()).abs() <= 0.005 + 1e-9);
+        }
+        // count, first key (2 bytes time and 3 bytes value), then 1 byte time and 1 byte value per key
+        assert_eq!(buffer.len(), 1 + 5 + 4 * 2);
+
+        let mut copy = LerpBtreeTimeline::<i32, f64, f64>::new(BTreeMap::new());
+        assert_eq!(codec.decode_into(&buffer, &mut copy), Ok(5));
+        assert!((copy.get_sample(88).unwrap() - timeline.get_sample(88).unwrap()).abs() < 0.01);
+    }
+}
+
+#[cfg(feature = "nalgebra_impl")]
+#[cfg(test)]
+mod tests_nalgebra {
+    use super::*;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn vectors() {
+        let keys = vec![(0_u16, Vector3::new(1.0_f32, 2.0, 3.0)), (5, Vector3::new(1.25, 2.0, 2.5))];
+        let codec = KeyCodec::quantized(0.25);
+        let mut buffer = Vec::new();
+        codec.encode(keys.iter().map(|(t, v)| (t, v)), &mut buffer);
+        assert_eq!(buffer.len(), 1 + 1 + 3 + 1 + 3);
+        assert_eq!(codec.decode(&buffer), Ok(keys));
+    }
+}
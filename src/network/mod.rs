@@ -0,0 +1,19 @@
+/*!
+ * Helpers to use timelines for networked values
+ * (ex: entities of a game server replicated on clients)
+ */
+use num_traits::{Float, NumCast};
+
+mod snapshot_buffer;
+mod reconciler;
+mod clock_sync;
+mod codec;
+
+pub use snapshot_buffer::{SnapshotBuffer, SnapshotBufferBuilder, SnapshotStats};
+pub use reconciler::Reconciler;
+pub use clock_sync::{ClockSync, ClockSyncBuilder};
+pub use codec::{KeyCodec, DeltaEncode, DecodeError};
+
+fn to_float<T: NumCast, F: Float>(time: T) -> F {
+    F::from(time).expect("time is representable as a float")
+}
@@ -0,0 +1,240 @@
+use crate::interface::{TimeLine, SampleError};
+use std::collections::BTreeMap;
+use std::ops::Add;
+
+/// Client-side prediction with server reconciliation
+///
+/// Each input of the client is simulated right away at its tick with the `simulate` callback,
+/// and kept with the predicted state until the server acknowledges the tick.
+/// When the authoritative state of the server differs from the prediction, the pending inputs are replayed
+/// from the authoritative state.
+///
+/// The visible timeline gets a data key for each predicted state. After a misprediction, the visible
+/// output does not jump: the visible data keys already set are left untouched, and the output is blended
+/// from the latest predicted tick to the corrected prediction `blend` ticks later
+/// (the same blend as [smooth_correct](../interface/trait.TimeLine.html#method.smooth_correct)).
+///
+/// TL: the visible timeline (ex: LerpBtreeTimeline)
+/// T: the type that encode ticks (ex: u32)
+/// I: the inputs of the client
+/// Sim: the simulation step, computing the state of a tick from the state of the previous tick and the input of the tick
+pub struct Reconciler<TL, T, I, Sim>
+where TL: TimeLine<T>, T: Clone {
+    visible: TL,
+    simulate: Sim,
+    blend: T,
+    blend_end: Option<T>,
+    acknowledged: (T, TL::Item),
+    pending: BTreeMap<T, (I, TL::Item)>,
+    corrections: usize
+}
+
+impl<TL, T, I, Sim> Reconciler<TL, T, I, Sim>
+where
+    TL: TimeLine<T>,
+    TL::Item: PartialEq,
+    T: Clone + Ord + Add<Output = T>,
+    Sim: FnMut(&TL::Item, &I) -> TL::Item {
+    /// Start predicting from the state known by both the client and the server at `tick`
+    pub fn new(mut visible: TL, blend: T, tick: T, state: TL::Item, simulate: Sim) -> Self {
+        visible.set_key(tick.clone(), state.clone());
+        Reconciler {
+            visible,
+            simulate,
+            blend,
+            blend_end: None,
+            acknowledged: (tick, state),
+            pending: BTreeMap::new(),
+            corrections: 0
+        }
+    }
+
+    /// Simulate `input` at `tick`, returning the predicted state
+    ///
+    /// Fails if `tick` is not after the latest predicted tick
+    pub fn predict(&mut self, tick: T, input: I) -> Result<TL::Item, SampleError> {
+        let (latest, state) = match self.pending.iter().next_back() {
+            Some((latest, (_, state))) => (latest, state),
+            None => (&self.acknowledged.0, &self.acknowledged.1)
+        };
+        SampleError::check_segment(latest, &tick)?;
+        let state = (self.simulate)(state, &input);
+        match &self.blend_end {
+            // the visible output is still blending toward the corrected prediction
+            Some(blend_end) if tick < *blend_end => {},
+            _ => self.visible.set_key(tick.clone(), state.clone())
+        }
+        self.pending.insert(tick, (input, state.clone()));
+        Ok(state)
+    }
+
+    /// Acknowledge the authoritative `state` of the server at `tick`
+    ///
+    /// Returns true if the pending inputs were replayed, because the prediction was wrong or there was
+    /// no prediction at `tick`. Only the wrong predictions count as corrections.
+    /// A tick older than the last acknowledged one is ignored
+    pub fn reconcile(&mut self, tick: T, state: TL::Item) -> bool {
+        if tick <= self.acknowledged.0 {
+            return false;
+        }
+        let mut pending = self.pending.split_off(&tick);
+        let predicted = match pending.iter().next() {
+            Some((first, _)) if *first == tick => pending.remove(&tick).map(|(_, predicted)| predicted),
+            _ => None
+        };
+        self.pending = pending;
+        self.acknowledged = (tick.clone(), state.clone());
+        match predicted {
+            Some(predicted) if predicted == state => return false,
+            Some(_) => self.corrections += 1,
+            None => {}
+        }
+
+        let mut corrected = state.clone();
+        let mut ticks = vec![tick.clone()];
+        for (tick, (input, predicted)) in self.pending.iter_mut() {
+            corrected = (self.simulate)(&corrected, input);
+            *predicted = corrected.clone();
+            ticks.push(tick.clone());
+        }
+        let latest = ticks.last().expect("the acknowledged tick is in ticks").clone();
+        let blend_end = latest.clone() + self.blend.clone();
+
+        // The corrected prediction is extrapolated to the end of the blend from the corrected keys,
+        // then the visible keys before the end of the blend are put back so the visible output never jumps
+        let mut shown = Vec::with_capacity(ticks.len());
+        let replayed = std::iter::once(state).chain(self.pending.values().map(|(_, predicted)| predicted.clone()));
+        for (tick, replayed) in ticks.iter().zip(replayed) {
+            shown.push(self.visible.remove_key(tick.clone()));
+            self.visible.set_key(tick.clone(), replayed);
+        }
+        let end_value = self.visible.try_get_sample(blend_end.clone());
+        for (tick, shown) in ticks.into_iter().zip(shown) {
+            self.visible.remove_key(tick.clone());
+            if let Some(shown) = shown {
+                self.visible.set_key(tick, shown);
+            }
+        }
+        match end_value {
+            Ok(end_value) => {
+                self.visible.set_key(blend_end.clone(), end_value);
+                self.blend_end = Some(blend_end);
+            },
+            Err(_) => {
+                // not enough data keys to blend, snap to the corrected prediction
+                self.visible.set_key(latest, corrected);
+                self.blend_end = None;
+            }
+        }
+        true
+    }
+
+    /// Latest predicted tick and state, or the acknowledged ones if no input is pending
+    pub fn latest(&self) -> (&T, &TL::Item) {
+        match self.pending.iter().next_back() {
+            Some((tick, (_, state))) => (tick, state),
+            None => (&self.acknowledged.0, &self.acknowledged.1)
+        }
+    }
+
+    /// Latest tick and state acknowledged by the server
+    pub fn acknowledged(&self) -> (&T, &TL::Item) {
+        (&self.acknowledged.0, &self.acknowledged.1)
+    }
+
+    /// Inputs not acknowledged by the server yet (oldest first)
+    pub fn pending_inputs(&self) -> impl Iterator<Item = (&T, &I)> {
+        self.pending.iter().map(|(tick, (input, _))| (tick, input))
+    }
+
+    /// Number of mispredictions corrected
+    pub fn corrections(&self) -> usize {
+        self.corrections
+    }
+
+    /// Timeline of the states to display
+    pub fn visible(&self) -> &TL {
+        &self.visible
+    }
+
+    pub fn visible_mut(&mut self) -> &mut TL {
+        &mut self.visible
+    }
+
+    pub fn into_inner(self) -> TL {
+        self.visible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    type Step = fn(&f64, &f64) -> f64;
+
+    fn step(position: &f64, velocity: &f64) -> f64 {
+        position + velocity
+    }
+
+    fn reconciler() -> Reconciler<LerpBtreeTimeline<u32, f64, f64>, u32, f64, Step> {
+        Reconciler::new(LerpBtreeTimeline::new(BTreeMap::new()), 4, 0, 0.0, step)
+    }
+
+    #[test]
+    fn right_prediction() {
+        let mut reconciler = reconciler();
+        for tick in 1..=5 {
+            assert_eq!(reconciler.predict(tick, 1.0), Ok(f64::from(tick)));
+        }
+        assert_eq!(reconciler.predict(5, 1.0), Err(SampleError::ZeroLengthSegment));
+        assert!(!reconciler.reconcile(2, 2.0));
+        assert_eq!(reconciler.pending_inputs().count(), 3);
+        assert_eq!(reconciler.acknowledged(), (&2, &2.0));
+        assert!(!reconciler.reconcile(1, 0.0));
+        assert_eq!(reconciler.corrections(), 0);
+        assert_eq!(reconciler.visible().get_sample(5), Some(5.0));
+    }
+
+    #[test]
+    fn misprediction_replays_inputs() {
+        let mut reconciler = reconciler();
+        for tick in 1..=6 {
+            reconciler.predict(tick, 1.0).unwrap();
+        }
+        // the server moved 2 units further at tick 2
+        assert!(reconciler.reconcile(2, 4.0));
+        assert_eq!(reconciler.latest(), (&6, &8.0));
+        assert_eq!(reconciler.corrections(), 1);
+        let visible = reconciler.visible();
+        // the visible output keeps the old prediction at the latest tick and catches up 4 ticks later
+        assert_eq!(visible.get_sample(6), Some(6.0));
+        assert_eq!(visible.get_sample(10), Some(12.0));
+        assert_eq!(visible.get_sample(8), Some(9.0));
+        // the past visible output is not rewritten, so it never goes backward
+        let samples: Vec<f64> = (0..=10).map(|tick| visible.get_sample(tick).unwrap()).collect();
+        assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", samples);
+        assert_eq!(visible.get_sample(2), Some(2.0));
+
+        // predictions during the blend do not disturb it
+        reconciler.predict(7, 1.0).unwrap();
+        assert_eq!(reconciler.visible().get_sample(8), Some(9.0));
+        for tick in 8..=11 {
+            reconciler.predict(tick, 1.0).unwrap();
+        }
+        assert_eq!(reconciler.visible().get_sample(10), Some(12.0));
+        assert_eq!(reconciler.visible().get_sample(11), Some(13.0));
+    }
+
+    #[test]
+    fn tick_without_prediction() {
+        let mut reconciler = reconciler();
+        reconciler.predict(2, 1.0).unwrap();
+        reconciler.predict(4, 1.0).unwrap();
+        // the inputs are replayed from the state of the server, but nothing was mispredicted
+        assert!(reconciler.reconcile(3, 3.0));
+        assert_eq!(reconciler.latest(), (&4, &4.0));
+        assert_eq!(reconciler.corrections(), 0);
+    }
+}
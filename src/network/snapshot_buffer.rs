@@ -0,0 +1,336 @@
+use crate::interface::{TimeLine, SampleError};
+use num_traits::{Float, NumCast};
+use std::marker::PhantomData;
+use super::to_float;
+
+/// Playback buffer of server snapshots, rendered a little in the past to hide network jitter
+///
+/// Snapshots are pushed with the server instant they describe and the local instant they arrived,
+/// they are rendered at `local_now - clock_offset - delay` (in server time):
+/// - `clock_offset` is the smoothed difference between arrival and server instants (latency + clock difference)
+/// - `delay` follows the observed jitter: `base_delay + jitter_factor * jitter`, at most `max_delay`.
+///   It changes at most `adaptation` time units per elapsed time unit, so the playback slightly speeds up or slows down
+///   instead of jumping, unless the delay is more than `snap_threshold` away from its target
+///
+/// When snapshots are lost the buffer extrapolates past the newest snapshot (according to the timeline sampler),
+/// at most `max_extrapolation` time units, then it holds the value.
+///
+/// The buffer keeps every snapshot, wrap the timeline in [Retention](../timelines/struct.Retention.html) to forget the old ones.
+///
+/// TL: the timeline holding the snapshots (ex: LerpBtreeTimeline)
+/// T: the type that encode time, for both server and local instants (ex: i64 milliseconds)
+/// F: the float type used to average the network statistics, durations are in T units
+pub struct SnapshotBuffer<TL, T, F = f64> {
+    timeline: TL,
+    base_delay: F,
+    jitter_factor: F,
+    max_delay: F,
+    adaptation: F,
+    snap_threshold: F,
+    max_extrapolation: F,
+    smoothing: F,
+    clock_offset: Option<F>,
+    jitter: F,
+    delay: F,
+    newest: Option<F>,
+    last_render: Option<(F,F)>,
+    stats: SnapshotStats,
+    _phantom: PhantomData<T>
+}
+
+/// Counters of a [SnapshotBuffer](struct.SnapshotBuffer.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SnapshotStats {
+    /// Snapshots pushed
+    pub received: usize,
+    /// Snapshots older than a snapshot received before
+    pub out_of_order: usize,
+    /// Snapshots older than the last rendered instant, they arrived too late to be interpolated
+    pub late: usize,
+    /// Renders between 2 snapshots
+    pub interpolated: usize,
+    /// Renders after the newest snapshot
+    pub extrapolated: usize,
+    /// Renders further than `max_extrapolation` after the newest snapshot
+    pub starved: usize,
+    /// Times the delay jumped to its target instead of adapting smoothly
+    pub snaps: usize
+}
+
+impl<TL, T, F> SnapshotBuffer<TL, T, F>
+where
+    TL: TimeLine<T>,
+    T: Copy + NumCast,
+    F: Float {
+    /// Buffer with a delay of `base_delay` plus 2 times the jitter
+    ///
+    /// The other durations default to multiples of `base_delay`: the delay is at most 10 times `base_delay`,
+    /// snaps when it is 5 times `base_delay` away from its target, and extrapolates at most 2 times `base_delay`
+    pub fn new(timeline: TL, base_delay: F) -> Self {
+        let two = F::one() + F::one();
+        let tenth = F::one() / F::from(10).unwrap();
+        SnapshotBuffer {
+            timeline,
+            base_delay,
+            jitter_factor: two,
+            max_delay: base_delay * F::from(10).unwrap(),
+            adaptation: tenth,
+            snap_threshold: base_delay * F::from(5).unwrap(),
+            max_extrapolation: base_delay * two,
+            smoothing: tenth,
+            clock_offset: None,
+            jitter: F::zero(),
+            delay: base_delay,
+            newest: None,
+            last_render: None,
+            stats: SnapshotStats::default(),
+            _phantom: PhantomData
+        }
+    }
+
+    pub fn builder(timeline: TL, base_delay: F) -> SnapshotBufferBuilder<TL, T, F> {
+        SnapshotBufferBuilder {
+            buffer: SnapshotBuffer::new(timeline, base_delay)
+        }
+    }
+
+    /// Add the snapshot of the server at instant `server_time`, received at local instant `arrival_time`
+    pub fn push(&mut self, server_time: T, arrival_time: T, value: TL::Item) {
+        self.stats.received += 1;
+        let (server, arrival) = (to_float::<T,F>(server_time), to_float::<T,F>(arrival_time));
+        let offset = arrival - server;
+        match self.clock_offset {
+            None => self.clock_offset = Some(offset),
+            Some(clock_offset) => {
+                let deviation = (offset - clock_offset).abs();
+                self.clock_offset = Some(clock_offset + (offset - clock_offset) * self.smoothing);
+                self.jitter = self.jitter + (deviation - self.jitter) * self.smoothing;
+            }
+        }
+        match self.newest {
+            Some(newest) if newest >= server => self.stats.out_of_order += 1,
+            _ => self.newest = Some(server)
+        }
+        if let Some((_, rendered)) = self.last_render {
+            if rendered >= server {
+                self.stats.late += 1;
+            }
+        }
+        self.timeline.set_key(server_time, value);
+    }
+
+    /// Server instant to render at local instant `local_now`, adapting the delay on the way
+    ///
+    /// Fails with `SampleError::Empty` until a snapshot is pushed.
+    /// The instant is converted back to T, so it is truncated for integer time types
+    pub fn render_time(&mut self, local_now: T) -> Result<T, SampleError> {
+        let local_now = to_float::<T,F>(local_now);
+        let (clock_offset, newest) = match (self.clock_offset, self.newest) {
+            (Some(clock_offset), Some(newest)) => (clock_offset, newest),
+            _ => return Err(SampleError::Empty)
+        };
+        let target = self.target_delay();
+        match self.last_render {
+            Some(_) if (target - self.delay).abs() > self.snap_threshold => {
+                self.stats.snaps += 1;
+                self.delay = target;
+            }
+            Some((last_now, _)) => {
+                let step = (local_now - last_now).max(F::zero()) * self.adaptation;
+                self.delay = self.delay + (target - self.delay).max(-step).min(step);
+            }
+            None => self.delay = target
+        }
+
+        let mut render = local_now - clock_offset - self.delay;
+        if render > newest + self.max_extrapolation {
+            self.stats.starved += 1;
+            render = newest + self.max_extrapolation;
+        } else if render > newest {
+            self.stats.extrapolated += 1;
+        } else {
+            self.stats.interpolated += 1;
+        }
+        self.last_render = Some((local_now, render));
+        T::from(render).ok_or(SampleError::OutOfRange)
+    }
+
+    /// Value to display at local instant `local_now`
+    pub fn try_sample_for_render(&mut self, local_now: T) -> Result<TL::Item, SampleError> {
+        let render = self.render_time(local_now)?;
+        self.timeline.try_get_sample(render)
+    }
+
+    /// Same as `try_sample_for_render` but returns None on failure
+    pub fn sample_for_render(&mut self, local_now: T) -> Option<TL::Item> {
+        self.try_sample_for_render(local_now).ok()
+    }
+
+    /// Delay the buffer is adapting to, according to the current jitter
+    pub fn target_delay(&self) -> F {
+        (self.base_delay + self.jitter_factor * self.jitter).min(self.max_delay)
+    }
+
+    /// Current delay between the newest possible instant and the rendered one
+    pub fn delay(&self) -> F {
+        self.delay
+    }
+
+    /// Smoothed deviation of the packets arrival
+    pub fn jitter(&self) -> F {
+        self.jitter
+    }
+
+    /// Smoothed difference between local and server instants, if a snapshot was received
+    pub fn clock_offset(&self) -> Option<F> {
+        self.clock_offset
+    }
+
+    pub fn stats(&self) -> SnapshotStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = SnapshotStats::default();
+    }
+
+    pub fn timeline(&self) -> &TL {
+        &self.timeline
+    }
+
+    pub fn timeline_mut(&mut self) -> &mut TL {
+        &mut self.timeline
+    }
+
+    pub fn into_inner(self) -> TL {
+        self.timeline
+    }
+}
+
+/// Builder of a configured [SnapshotBuffer](struct.SnapshotBuffer.html)
+pub struct SnapshotBufferBuilder<TL, T, F> {
+    buffer: SnapshotBuffer<TL, T, F>
+}
+
+impl<TL, T, F> SnapshotBufferBuilder<TL, T, F>
+where
+    TL: TimeLine<T>,
+    T: Copy + NumCast,
+    F: Float {
+    /// How many times the jitter is added to the delay
+    pub fn jitter_factor(mut self, factor: F) -> Self {
+        self.buffer.jitter_factor = factor;
+        self
+    }
+
+    pub fn max_delay(mut self, delay: F) -> Self {
+        self.buffer.max_delay = delay;
+        self
+    }
+
+    /// How fast the delay changes, in time units per elapsed time unit (ex: 0.1 plays 10% faster or slower)
+    pub fn adaptation(mut self, rate: F) -> Self {
+        self.buffer.adaptation = rate;
+        self
+    }
+
+    /// Difference with the target delay above which the delay jumps to its target
+    pub fn snap_threshold(mut self, threshold: F) -> Self {
+        self.buffer.snap_threshold = threshold;
+        self
+    }
+
+    /// How far after the newest snapshot the buffer can render
+    pub fn max_extrapolation(mut self, distance: F) -> Self {
+        self.buffer.max_extrapolation = distance;
+        self
+    }
+
+    /// Weight of a new snapshot in the clock offset and jitter averages, between 0 and 1
+    pub fn smoothing(mut self, weight: F) -> Self {
+        self.buffer.smoothing = weight;
+        self
+    }
+
+    pub fn build(self) -> SnapshotBuffer<TL, T, F> {
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    type Buffer = SnapshotBuffer<LerpBtreeTimeline<i32, f64, f64>, i32>;
+
+    // times in milliseconds
+    fn buffer() -> Buffer {
+        SnapshotBuffer::new(LerpBtreeTimeline::new(BTreeMap::new()), 100.0)
+    }
+
+    #[test]
+    fn render_in_the_past() {
+        let mut buffer = buffer();
+        assert_eq!(buffer.sample_for_render(0), None);
+        // server clock is 100s ahead, packets take 50ms
+        for i in 0..10 {
+            let server_time = 100_000 + i * 50;
+            buffer.push(server_time, server_time - 99_950, server_time as f64);
+        }
+        assert_eq!(buffer.jitter(), 0.0);
+        assert_eq!(buffer.clock_offset(), Some(-99_950.0));
+        assert_eq!(buffer.render_time(500), Ok(100_350));
+        assert_eq!(buffer.sample_for_render(500), Some(100_350.0));
+        assert_eq!(buffer.stats().interpolated, 2);
+    }
+
+    #[test]
+    fn extrapolation_and_starvation() {
+        let mut buffer = buffer();
+        buffer.push(0, 0, 0.0);
+        buffer.push(1000, 1000, 1000.0);
+        assert_eq!(buffer.sample_for_render(1200), Some(1100.0));
+        assert_eq!(buffer.sample_for_render(2000), Some(1200.0));
+        let stats = buffer.stats();
+        assert_eq!((stats.extrapolated, stats.starved), (1, 1));
+        // a snapshot older than the rendered instant is late
+        buffer.push(500, 2100, 500.0);
+        let stats = buffer.stats();
+        assert_eq!((stats.received, stats.out_of_order, stats.late), (3, 1, 1));
+    }
+
+    #[test]
+    fn adaptive_delay() {
+        let mut buffer: Buffer = SnapshotBuffer::builder(LerpBtreeTimeline::new(BTreeMap::new()), 100.0)
+            .adaptation(0.1)
+            .smoothing(0.5)
+            .build();
+        buffer.push(0, 0, 0.0);
+        buffer.push(1000, 1200, 1000.0);
+        // offset moved halfway, jitter is half the deviation
+        assert_eq!(buffer.clock_offset(), Some(100.0));
+        assert_eq!(buffer.jitter(), 100.0);
+        assert_eq!(buffer.target_delay(), 300.0);
+        buffer.render_time(1000).unwrap();
+        assert_eq!(buffer.delay(), 300.0);
+
+        // jitter drops, the delay slowly follows
+        for i in 2..40 {
+            buffer.push(i * 1000, i * 1000 + 100, (i * 1000) as f64);
+        }
+        assert!(buffer.target_delay() < 100.1);
+        buffer.render_time(2000).unwrap();
+        assert_eq!(buffer.delay(), 200.0);
+        buffer.render_time(2500).unwrap();
+        assert_eq!(buffer.delay(), 150.0);
+        assert_eq!(buffer.stats().snaps, 0);
+
+        // jitter spikes far above the delay, which is capped to 10 times the base delay
+        buffer.push(40_000, 45_000, 40_000.0);
+        buffer.render_time(3000).unwrap();
+        assert_eq!(buffer.stats().snaps, 1);
+        assert_eq!(buffer.delay(), 1000.0);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::interface::{DataSampler, DerivativeSampler, SampleError};
+use crate::samplers::{Lerp, Slope};
+use num_traits::Float;
+use std::ops::{Add, Sub, Mul};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// Implement DataSampler for cubic (Catmull-Rom) inter/extrapolation strategy
+///
+/// Unlike the [LerpSampler](struct.LerpSampler.html), the resulting curve has no angle at the data keys.
+/// It is evaluated with the Barry-Goldman pyramidal formulation: a cascade of `lerp` only,
+/// so any `V` implementing [Lerp](trait.Lerp.html) can be sampled, and the keys do not need to be evenly spaced.
+///
+/// for the interpolation the algorithme use the 2 past values and the 2 future values,
+/// if one side only has 1 value, the quadratic curve going through the 3 available values is used,
+/// if both sides only have 1 value, it fallback to a linear interpolation.
+///
+/// for the extrapolation the algorithme fits the parabola going through the last (or first) 3 values,
+/// the line through the last (or first) 2 values if there is only 2, and it fails with only 1 value.
+/// The last (or first) segment is interpolated with the same parabola, so the extrapolation continues it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CubicSampler<F = f64> {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _phantom: std::marker::PhantomData<F>
+}
+
+impl<F: Float> CubicSampler<F> {
+    pub fn new() -> Self {
+        CubicSampler {
+            _phantom: std::marker::PhantomData
+        }
+    }
+}
+
+impl<F: Float> Default for CubicSampler<F> {
+    fn default() -> Self {
+        CubicSampler::new()
+    }
+}
+
+/// Data keys selected to inter/extrapolate, their times are offsets from the oldest time involved
+enum Keys<F, V> {
+    Linear([(F, V); 2]),
+    Quadratic([(F, V); 3]),
+    Cubic([(F, V); 4])
+}
+
+impl<F: Float> CubicSampler<F> {
+    /// Select the data keys to inter/extrapolate `time` with, and convert `time` to an offset from the same origin
+    fn keys<'a, T, V>(past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<(Keys<F, V>, F), SampleError>
+    where
+        T: Sub<Output = T> + Clone + PartialOrd + Into<F> + 'a,
+        V: Clone + 'a {
+        // Every time is converted to an offset from the oldest time involved,
+        // so that the substractions never go negative (unsigned time types)
+        match (past_values.next(), future_values.next()) {
+
+            //Interpolation
+            (Some(p0), Some(f0)) => {
+                SampleError::check_order(p0.0, &time)?;
+                SampleError::check_order(&time, f0.0)?;
+                match (past_values.next(), future_values.next()) {
+                    (Some(p1), Some(f1)) => {
+                        check_keys(&[p1.0, p0.0, f0.0, f1.0])?;
+                        let origin = p1.0.clone();
+                        let keys = [offset(p1, &origin), offset(p0, &origin), offset(f0, &origin), offset(f1, &origin)];
+                        Ok((Keys::Cubic(keys), (time - origin).into()))
+                    },
+                    (Some(p1), None) => {
+                        check_keys(&[p1.0, p0.0, f0.0])?;
+                        let origin = p1.0.clone();
+                        let keys = [offset(p1, &origin), offset(p0, &origin), offset(f0, &origin)];
+                        Ok((Keys::Quadratic(keys), (time - origin).into()))
+                    },
+                    (None, Some(f1)) => {
+                        check_keys(&[p0.0, f0.0, f1.0])?;
+                        let origin = p0.0.clone();
+                        let keys = [offset(p0, &origin), offset(f0, &origin), offset(f1, &origin)];
+                        Ok((Keys::Quadratic(keys), (time - origin).into()))
+                    },
+                    (None, None) => {
+                        check_keys(&[p0.0, f0.0])?;
+                        let origin = p0.0.clone();
+                        let keys = [offset(p0, &origin), offset(f0, &origin)];
+                        Ok((Keys::Linear(keys), (time - origin).into()))
+                    }
+                }
+            },
+
+            //Extrapolation future from the last 3 (or 2) values
+            (Some(p0), None) => {
+                SampleError::check_order(p0.0, &time)?;
+                match (past_values.next(), past_values.next()) {
+                    (Some(p1), Some(p2)) => {
+                        check_keys(&[p2.0, p1.0, p0.0])?;
+                        let origin = p2.0.clone();
+                        let keys = [offset(p2, &origin), offset(p1, &origin), offset(p0, &origin)];
+                        Ok((Keys::Quadratic(keys), (time - origin).into()))
+                    },
+                    (Some(p1), None) => {
+                        check_keys(&[p1.0, p0.0])?;
+                        let origin = p1.0.clone();
+                        let keys = [offset(p1, &origin), offset(p0, &origin)];
+                        Ok((Keys::Linear(keys), (time - origin).into()))
+                    },
+                    _ => Err(SampleError::NotEnoughKeys)
+                }
+            },
+
+            //Extrapolation past from the next 3 (or 2) values
+            (None, Some(f0)) => {
+                SampleError::check_order(&time, f0.0)?;
+                match (future_values.next(), future_values.next()) {
+                    (Some(f1), Some(f2)) => {
+                        check_keys(&[f0.0, f1.0, f2.0])?;
+                        let keys = [offset(f0, &time), offset(f1, &time), offset(f2, &time)];
+                        Ok((Keys::Quadratic(keys), F::zero()))
+                    },
+                    (Some(f1), None) => {
+                        check_keys(&[f0.0, f1.0])?;
+                        let keys = [offset(f0, &time), offset(f1, &time)];
+                        Ok((Keys::Linear(keys), F::zero()))
+                    },
+                    _ => Err(SampleError::NotEnoughKeys)
+                }
+            },
+
+            //No value to (Inter/Extra)polate
+            (None, None) => Err(SampleError::Empty)
+        }
+    }
+
+    /// Value and derivatives of the curve at `time`
+    fn jet<'a, T, V>(past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Jet<V, V::Output>, SampleError>
+    where
+        T: Sub<Output = T> + Clone + PartialOrd + Into<F> + 'a,
+        V: Clone + Lerp<F> + Slope<F> + 'a,
+        V::Output: Clone + Lerp<F> + Slope<F, Output = V::Output> + Add<Output = V::Output> + Mul<F, Output = V::Output> {
+        Ok(match Self::keys(past_values, future_values, time)? {
+            (Keys::Linear(keys), t) => linear_jet(keys, t),
+            (Keys::Quadratic(keys), t) => quadratic_jet(keys, t),
+            (Keys::Cubic(keys), t) => catmull_rom_jet(keys, t)
+        })
+    }
+}
+
+impl<T, V, F> DataSampler<T,V> for CubicSampler<F>
+where
+    F: Float,
+    T: Sub<Output = T> + Clone + PartialOrd + Into<F>,
+    V: Clone + Lerp<F> {
+    fn try_sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<V, SampleError>
+    where T: 'a, V: 'a {
+        Ok(match Self::keys(past_values, future_values, time)? {
+            (Keys::Linear(keys), t) => linear(keys, t),
+            (Keys::Quadratic(keys), t) => quadratic(keys, t),
+            (Keys::Cubic(keys), t) => catmull_rom(keys, t)
+        })
+    }
+}
+
+/// The derivatives are the exact derivatives of the curve sampled by `try_sample`
+impl<T, V, F> DerivativeSampler<T,V> for CubicSampler<F>
+where
+    F: Float,
+    T: Sub<Output = T> + Clone + PartialOrd + Into<F>,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Clone + Lerp<F> + Slope<F, Output = V::Output> + Add<Output = V::Output> + Mul<F, Output = V::Output> {
+    type Derivative = V::Output;
+    fn try_sample_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a {
+        Ok(Self::jet(past_values, future_values, time)?.derivative)
+    }
+    fn try_sample_second_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a {
+        Ok(Self::jet(past_values, future_values, time)?.second_derivative)
+    }
+}
+
+/// Check that the key times are strictly increasing
+fn check_keys<T: PartialOrd>(times: &[&T]) -> Result<(), SampleError> {
+    times.windows(2).try_for_each(|pair| SampleError::check_segment(pair[0], pair[1]))
+}
+
+/// Convert the time of a data key to an offset from `origin`, and take a copy of its value
+fn offset<T, V, F>((time, value): (&T, &V), origin: &T) -> (F, V)
+where
+    T: Sub<Output = T> + Clone + Into<F>,
+    V: Clone {
+    ((time.clone() - origin.clone()).into(), value.clone())
+}
+
+/// Position of `t` in the segment [t0, t1], 0 at t0 and 1 at t1
+fn ratio<F: Float>(t: F, t0: F, t1: F) -> F {
+    (t - t0) / (t1 - t0)
+}
+
+/// Value at `t` of the line going through the 2 keys
+fn linear<V: Lerp<F>, F: Float>([(t0, v0), (t1, v1)]: [(F, V); 2], t: F) -> V {
+    v0.lerp(v1, ratio(t, t0, t1))
+}
+
+/// Value at `t` of the parabola going through the 3 keys (Neville's algorithm)
+fn quadratic<V: Clone + Lerp<F>, F: Float>([(t0, v0), (t1, v1), (t2, v2)]: [(F, V); 3], t: F) -> V {
+    let a0 = v0.lerp(v1.clone(), ratio(t, t0, t1));
+    let a1 = v1.lerp(v2, ratio(t, t1, t2));
+    a0.lerp(a1, ratio(t, t0, t2))
+}
+
+/// Value at `t` of the Catmull-Rom segment between the 2 middle keys (Barry-Goldman algorithm)
+fn catmull_rom<V: Clone + Lerp<F>, F: Float>([(t0, v0), (t1, v1), (t2, v2), (t3, v3)]: [(F, V); 4], t: F) -> V {
+    let a0 = v0.lerp(v1.clone(), ratio(t, t0, t1));
+    let a1 = v1.lerp(v2.clone(), ratio(t, t1, t2));
+    let a2 = v2.lerp(v3, ratio(t, t2, t3));
+    let b0 = a0.lerp(a1.clone(), ratio(t, t0, t2));
+    let b1 = a1.lerp(a2, ratio(t, t1, t3));
+    b0.lerp(b1, ratio(t, t1, t2))
+}
+
+/// Value of a curve and its first and second derivatives at the same instant
+#[derive(Clone)]
+struct Jet<V, D> {
+    value: V,
+    derivative: D,
+    second_derivative: D
+}
+
+/// Same as `linear`, with the derivatives
+fn linear_jet<V, F>([(t0, v0), (t1, v1)]: [(F, V); 2], t: F) -> Jet<V, V::Output>
+where
+    F: Float,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Clone + Mul<F, Output = V::Output> {
+    let derivative = v0.clone().slope(v1.clone(), t1 - t0);
+    Jet {
+        value: v0.lerp(v1, ratio(t, t0, t1)),
+        second_derivative: derivative.clone() * F::zero(),
+        derivative
+    }
+}
+
+/// Jet of `left.lerp(right, ratio(t, t0, t1))`
+fn lerp_jet<V, F>(left: Jet<V, V::Output>, right: Jet<V, V::Output>, t0: F, t1: F, t: F) -> Jet<V, V::Output>
+where
+    F: Float,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Clone + Lerp<F> + Slope<F, Output = V::Output> + Add<Output = V::Output> + Mul<F, Output = V::Output> {
+    let s = ratio(t, t0, t1);
+    let duration = t1 - t0;
+    Jet {
+        value: left.value.clone().lerp(right.value.clone(), s),
+        derivative: left.derivative.clone().lerp(right.derivative.clone(), s)
+            + left.value.slope(right.value, duration),
+        second_derivative: left.second_derivative.lerp(right.second_derivative, s)
+            + left.derivative.slope(right.derivative, duration) * (F::one() + F::one())
+    }
+}
+
+/// Same as `quadratic`, with the derivatives
+fn quadratic_jet<V, F>([k0, k1, k2]: [(F, V); 3], t: F) -> Jet<V, V::Output>
+where
+    F: Float,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Clone + Lerp<F> + Slope<F, Output = V::Output> + Add<Output = V::Output> + Mul<F, Output = V::Output> {
+    let (t0, t2) = (k0.0, k2.0);
+    let a0 = linear_jet([k0, k1.clone()], t);
+    let a1 = linear_jet([k1, k2], t);
+    lerp_jet(a0, a1, t0, t2, t)
+}
+
+/// Same as `catmull_rom`, with the derivatives
+fn catmull_rom_jet<V, F>([k0, k1, k2, k3]: [(F, V); 4], t: F) -> Jet<V, V::Output>
+where
+    F: Float,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Clone + Lerp<F> + Slope<F, Output = V::Output> + Add<Output = V::Output> + Mul<F, Output = V::Output> {
+    let (t0, t1, t2, t3) = (k0.0, k1.0, k2.0, k3.0);
+    let a0 = linear_jet([k0, k1.clone()], t);
+    let a1 = linear_jet([k1, k2.clone()], t);
+    let a2 = linear_jet([k2, k3], t);
+    let b0 = lerp_jet(a0, a1.clone(), t0, t2, t);
+    let b1 = lerp_jet(a1, a2, t1, t3, t);
+    lerp_jet(b0, b1, t1, t2, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys of the parabola v = t², a cubic sampler must reproduce it exactly
+    fn square(times: &[u32]) -> Vec<(u32, f64)> {
+        times.iter().map(|&t| (t, (t * t) as f64)).collect()
+    }
+
+    fn keys<T,V>(input: &[(T,V)]) -> impl Iterator<Item = (&T,&V)> {
+        input.iter().map(|(t,v)| (t,v))
+    }
+
+    #[test]
+    fn interpolation_success() {
+        let past = square(&[2, 0]);
+        let future = square(&[4, 6]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 3), Some(9.0));
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 4), Some(16.0));
+    }
+
+    #[test]
+    fn interpolation_catmull_rom() {
+        let past = vec![(1.0, 0.0), (0.0, 0.0)];
+        let future = vec![(2.0, 1.0), (3.0, 1.0)];
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 1.0), Some(0.0));
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 1.25), Some(0.203125));
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 1.5), Some(0.5));
+    }
+
+    #[test]
+    fn interpolation_quadratic_fallback() {
+        let past = square(&[2]);
+        let future = square(&[4, 6]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 3), Some(9.0));
+        let past = square(&[2, 0]);
+        let future = square(&[4]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 3), Some(9.0));
+    }
+
+    #[test]
+    fn interpolation_linear_fallback() {
+        let past = square(&[2]);
+        let future = square(&[4]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 3), Some(10.0));
+    }
+
+    #[test]
+    fn extrapolation_future_success() {
+        let past = square(&[4, 2, 0]);
+        let future = Vec::new();
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 5), Some(25.0));
+        let past = square(&[4, 2]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 5), Some(22.0));
+    }
+
+    #[test]
+    fn extrapolation_past_success() {
+        let past = Vec::new();
+        let future = square(&[2, 4, 6]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 1), Some(1.0));
+        let future = square(&[2, 4]);
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 1), Some(-2.0));
+    }
+
+    #[test]
+    fn extrapolation_failed() {
+        let empty = Vec::new();
+        let single = square(&[2]);
+        assert_eq!(CubicSampler::<f64>::new().try_sample(&mut keys(&single), &mut keys(&empty), 3), Err(SampleError::NotEnoughKeys));
+        assert_eq!(CubicSampler::<f64>::new().try_sample(&mut keys(&empty), &mut keys(&single), 1), Err(SampleError::NotEnoughKeys));
+        assert_eq!(CubicSampler::<f64>::new().try_sample(&mut keys(&empty), &mut keys(&empty), 1), Err(SampleError::Empty));
+    }
+
+    #[test]
+    fn derivatives() {
+        let sampler = CubicSampler::<f64>::new();
+        let past = square(&[2, 0]);
+        let future = square(&[4, 6]);
+        assert_eq!(sampler.try_sample_derivative(&mut keys(&past), &mut keys(&future), 3), Ok(6.0));
+        assert_eq!(sampler.try_sample_second_derivative(&mut keys(&past), &mut keys(&future), 3), Ok(2.0));
+        let longer_future = square(&[4, 6, 8]);
+        assert_eq!(sampler.try_sample_derivative(&mut keys(&[]), &mut keys(&longer_future), 3), Ok(6.0));
+        let longer_past = square(&[2, 1, 0]);
+        assert_eq!(sampler.try_sample_second_derivative(&mut keys(&longer_past), &mut keys(&[]), 3), Ok(2.0));
+        assert_eq!(sampler.try_sample_derivative(&mut keys(&past[..1]), &mut keys(&future[..1]), 3), Ok(6.0));
+        assert_eq!(sampler.try_sample_second_derivative(&mut keys(&past[..1]), &mut keys(&future[..1]), 3), Ok(0.0));
+    }
+
+    #[test]
+    fn derivative_continuous_at_keys() {
+        let sampler = CubicSampler::<f64>::new();
+        let data = [(0.0, 0.0), (1.0, 0.0), (2.0, 1.0), (3.0, 1.0), (4.0, 3.0)];
+        // at 2.0 the key is a future value, right before it the same key is the first future value too
+        let before: f64 = sampler.try_sample_derivative(&mut keys(&[data[1], data[0]]), &mut keys(&data[2..]), 1.999999).unwrap();
+        let after: f64 = sampler.try_sample_derivative(&mut keys(&[data[2], data[1], data[0]]), &mut keys(&data[3..]), 2.000001).unwrap();
+        assert!((before - after).abs() < 1e-4);
+    }
+
+    #[test]
+    fn unordered_failed() {
+        let past = square(&[2, 4]);
+        let future = square(&[6, 6]);
+        assert_eq!(CubicSampler::<f64>::new().try_sample(&mut keys(&past), &mut keys(&future), 5), Err(SampleError::OutOfOrder));
+        let past = square(&[4, 2]);
+        assert_eq!(CubicSampler::<f64>::new().try_sample(&mut keys(&past), &mut keys(&future), 5), Err(SampleError::ZeroLengthSegment));
+    }
+}
+
+#[cfg(feature = "nalgebra_impl")]
+#[cfg(test)]
+mod tests_nalgebra {
+    use super::*;
+    use nalgebra::Vector2;
+
+    fn keys<T,V>(input: &[(T,V)]) -> impl Iterator<Item = (&T,&V)> {
+        input.iter().map(|(t,v)| (t,v))
+    }
+
+    #[test]
+    fn interpolation_success() {
+        let past = vec![(2_u32, Vector2::new(4.0, 2.0)), (0, Vector2::new(0.0, 0.0))];
+        let future = vec![(4_u32, Vector2::new(16.0, 4.0)), (6, Vector2::new(36.0, 6.0))];
+        assert_eq!(CubicSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 3), Some(Vector2::new(9.0, 3.0)));
+    }
+}
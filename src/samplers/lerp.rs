@@ -1,10 +1,16 @@
-use crate::interface::DataSampler;
+use crate::interface::{DataSampler, DerivativeSampler, AggregateSampler, Interpolation, SampleError};
+use crate::samplers::Slope;
 #[cfg(feature = "nalgebra_impl")]
 use nalgebra::{MatrixMN, Dim, Scalar, allocator::Allocator, DefaultAllocator};
 use num_traits::Float;
-use std::ops::{Add, Sub};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use std::ops::{Sub, Mul};
 
 /// Items that can be linteraly inter/extrapoled acording to float type F
+/// 
+/// With the `nalgebra_impl` feature, nalgebra rotations (UnitQuaternion, UnitComplex, Rotation2, Rotation3)
+/// and isometries (Isometry2, Isometry3) are interpolated spherically (slerp) along the shortest path
 pub trait Lerp<F> {
     /// interpolate self with other proportionally to t
     /// t should be a float f32 or f64 (or maybe an other float representation)
@@ -22,7 +28,7 @@ pub trait Lerp<F> {
 impl<N, R, C, F> Lerp<F> for MatrixMN<N, R, C>
 where
     N: Clone + Scalar + Lerp<F>,
-    MatrixMN<N, R, C>: Clone + Add<Output=MatrixMN<N, R, C>> + Sub<Output=MatrixMN<N, R, C>>, 
+    MatrixMN<N, R, C>: Clone + std::ops::Add<Output=MatrixMN<N, R, C>> + Sub<Output=MatrixMN<N, R, C>>, 
     F: Float,
     R: Dim,
     C: Dim,
@@ -78,48 +84,342 @@ impl_lerp!(f64, u128);
 impl_lerp!(f64, i128);
 
 
+/// Policy of the [LerpSampler](struct.LerpSampler.html) when sampling before the first data key
+/// or after the last one. The durations are in time units converted to F
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Extrapolation<F> {
+    /// Extrapolate linearly forever
+    Unbounded,
+    /// Never extrapolate, fails with `SampleError::OutOfRange`
+    Refuse,
+    /// Extrapolate linearly up to the given duration, then fails with `SampleError::OutOfRange`
+    Limit(F),
+    /// Extrapolate linearly up to the given duration, then the value stays the same
+    Freeze(F),
+    /// The rate of change decays linearly to zero over the given duration, then the value stays the same
+    Decay(F)
+}
+
+impl<F: Float> Extrapolation<F> {
+    /// Convert the `distance` between the sampled instant and the nearest data key to
+    /// the distance the linear extrapolation should actually cover
+    pub fn apply(&self, distance: F) -> Result<F, SampleError> {
+        Ok(self.curve(distance)?.0)
+    }
+
+    /// Shortest `distance` at which the extrapolation covers the distance `covered`, the inverse of `apply`
+    fn distance(&self, covered: F) -> F {
+        match *self {
+            Extrapolation::Freeze(max) => covered.min(max),
+            Extrapolation::Decay(duration) if covered + covered >= duration => duration,
+            // d - d²/2D = covered
+            Extrapolation::Decay(duration) => duration - (duration * duration - (duration + duration) * covered).max(F::zero()).sqrt(),
+            _ => covered
+        }
+    }
+
+    /// Integral of `apply` from the data key to `distance`
+    fn covered_integral(&self, distance: F) -> F {
+        let two = F::one() + F::one();
+        match *self {
+            Extrapolation::Freeze(max) if distance >= max => max * max / two + max * (distance - max),
+            Extrapolation::Decay(duration) if distance >= duration => duration * duration / (two + F::one()) + duration / two * (distance - duration),
+            // integral of d - d²/2D
+            Extrapolation::Decay(duration) => distance * distance / two - distance * distance * distance / (two * (two + F::one()) * duration),
+            _ => distance * distance / two
+        }
+    }
+
+    /// Same as `apply` but also give the first and second derivative of the covered distance
+    /// relative to `distance`
+    fn curve(&self, distance: F) -> Result<(F, F, F), SampleError> {
+        let (zero, one) = (F::zero(), F::one());
+        match *self {
+            Extrapolation::Unbounded => Ok((distance, one, zero)),
+            Extrapolation::Refuse if distance > zero => Err(SampleError::OutOfRange),
+            Extrapolation::Refuse => Ok((distance, one, zero)),
+            Extrapolation::Limit(max) if distance > max => Err(SampleError::OutOfRange),
+            Extrapolation::Limit(_) => Ok((distance, one, zero)),
+            Extrapolation::Freeze(max) if distance >= max => Ok((max, zero, zero)),
+            Extrapolation::Freeze(_) => Ok((distance, one, zero)),
+            Extrapolation::Decay(duration) if distance >= duration => Ok((duration / (one + one), zero, zero)),
+            // integral of the decaying rate of change: d - d²/2D
+            Extrapolation::Decay(duration) => Ok((
+                distance - distance * distance / (duration + duration),
+                one - distance / duration,
+                -one / duration))
+        }
+    }
+}
+
+/// The 2 data keys used to inter/extrapolate and the position of the sampled instant relative to them
+struct Segment<'a, V, F> {
+    start: &'a V,
+    end: &'a V,
+    duration: F,
+    /// position on the segment, 0 at `start` and 1 at `end`
+    ratio: F,
+    /// derivative of `ratio` multiplied by `duration`,
+    /// 1 unless an extrapolation policy slows the extrapolation down
+    speed: F,
+    /// second derivative of `ratio` multiplied by `duration`
+    acceleration: F
+}
+
 /// Implement DataSampler for linear inter/extrapolation strategy
 /// 
 /// for the interpolation to succeed the algorithme need at least 2 values
 /// from both `past_values` and `future_values` (interpolation) or either one (extrapolation)
-#[allow(dead_code)]
+/// 
+/// By default the extrapolation is unbounded, use [builder](struct.LerpSampler.html#method.builder)
+/// to choose an other [Extrapolation](enum.Extrapolation.html) policy for the past and the future
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LerpSampler<F = f64> {
-    _phantom: std::marker::PhantomData<F>
+    past_extrapolation: Extrapolation<F>,
+    future_extrapolation: Extrapolation<F>
 }
 
-impl<T, V, F> DataSampler<T,V> for LerpSampler<F>
-where
-    F: Float,
-    T: Sub<Output = T> + Clone + Into<F>,
-    V: Lerp<F> {
-    fn sample(past_values: &mut Iterator<Item = (T,V)>, future_values: &mut Iterator<Item = (T,V)>, time: T) -> Option<V> {
+impl<F: Float> LerpSampler<F> {
+    /// Sampler with unbounded extrapolation
+    pub fn new() -> Self {
+        LerpSampler {
+            past_extrapolation: Extrapolation::Unbounded,
+            future_extrapolation: Extrapolation::Unbounded
+        }
+    }
+
+    pub fn builder() -> LerpSamplerBuilder<F> {
+        LerpSamplerBuilder {
+            sampler: LerpSampler::new()
+        }
+    }
+
+    /// Policy used before the first data key
+    pub fn past_extrapolation(&self) -> Extrapolation<F> {
+        self.past_extrapolation
+    }
+
+    /// Policy used after the last data key
+    pub fn future_extrapolation(&self) -> Extrapolation<F> {
+        self.future_extrapolation
+    }
+}
+
+impl<F: Float> Default for LerpSampler<F> {
+    fn default() -> Self {
+        LerpSampler::new()
+    }
+}
+
+/// Builder of a configured [LerpSampler](struct.LerpSampler.html)
+pub struct LerpSamplerBuilder<F> {
+    sampler: LerpSampler<F>
+}
+
+impl<F: Float> LerpSamplerBuilder<F> {
+    /// Allow (default) or forbid the extrapolation before the first data key and after the last one
+    pub fn extrapolate(self, extrapolate: bool) -> Self {
+        self.extrapolation(if extrapolate { Extrapolation::Unbounded } else { Extrapolation::Refuse })
+    }
+
+    /// Forbid the extrapolation further than `distance` time units before the first data key or after the last one
+    pub fn max_extrapolation(self, distance: F) -> Self {
+        self.extrapolation(Extrapolation::Limit(distance))
+    }
+
+    /// Policy used both before the first data key and after the last one
+    pub fn extrapolation(self, policy: Extrapolation<F>) -> Self {
+        self.past_extrapolation(policy).future_extrapolation(policy)
+    }
+
+    /// Policy used before the first data key
+    pub fn past_extrapolation(mut self, policy: Extrapolation<F>) -> Self {
+        self.sampler.past_extrapolation = policy;
+        self
+    }
+
+    /// Policy used after the last data key
+    pub fn future_extrapolation(mut self, policy: Extrapolation<F>) -> Self {
+        self.sampler.future_extrapolation = policy;
+        self
+    }
+
+    pub fn build(self) -> LerpSampler<F> {
+        self.sampler
+    }
+}
+
+impl<F: Float> LerpSampler<F> {
+    /// Find the segment to inter/extrapolate `time` on
+    fn segment<'a, T, V>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Segment<'a, V, F>, SampleError>
+    where
+        T: Sub<Output = T> + Clone + PartialOrd + Into<F> + 'a {
         match (past_values.next(), future_values.next()) {
 
             //Interpolation
-            (Some((pt, pv)), Some((ft, fv))) => Some(pv.lerp(fv, (time - pt.clone()).into()/(ft - pt).into())),
-
-            //Try Extrapolation future
-            (Some((pt, pv)),None) => match past_values.next() {
-                //Extrapolation future from the last 2 values
-                Some((pt2, pv2)) => Some(pv2.lerp(pv, (time - pt2.clone()).into()/(pt - pt2).into())),
-                //Extrapolation failed
-                None => None
+            (Some((pt, pv)), Some((ft, fv))) => {
+                SampleError::check_order(pt, &time)?;
+                SampleError::check_order(&time, ft)?;
+                SampleError::check_segment(pt, ft)?;
+                let duration = (ft.clone() - pt.clone()).into();
+                Ok(Segment { start: pv, end: fv, duration, ratio: (time - pt.clone()).into()/duration, speed: F::one(), acceleration: F::zero() })
+            },
+
+            //Extrapolation future from the last 2 values
+            (Some((pt, pv)),None) => {
+                let (pt2, pv2) = past_values.next().ok_or(SampleError::NotEnoughKeys)?;
+                SampleError::check_order(pt, &time)?;
+                SampleError::check_segment(pt2, pt)?;
+                let (distance, speed, acceleration) = self.future_extrapolation.curve((time - pt.clone()).into())?;
+                let duration = (pt.clone() - pt2.clone()).into();
+                Ok(Segment { start: pv2, end: pv, duration, ratio: (duration + distance)/duration, speed, acceleration })
             },
 
-            //Try Extrapolation past
-            (None, Some((ft, fv))) => match future_values.next() {
-                //Extrapolation past from the next 2 values
-                Some((ft2, fv2)) => Some(fv.lerp(fv2, (time - ft.clone()).into()/(ft2 - ft).into())),
-                //Extrapolation failed
-                None => None
+            //Extrapolation past from the next 2 values
+            (None, Some((ft, fv))) => {
+                let (ft2, fv2) = future_values.next().ok_or(SampleError::NotEnoughKeys)?;
+                SampleError::check_order(&time, ft)?;
+                SampleError::check_segment(ft, ft2)?;
+                // the distance grows when time goes backward
+                let (distance, speed, acceleration) = self.past_extrapolation.curve((ft.clone() - time).into())?;
+                let duration = (ft2.clone() - ft.clone()).into();
+                Ok(Segment { start: fv, end: fv2, duration, ratio: -distance/duration, speed, acceleration: -acceleration })
             },
 
             //No value to (Inter/Extra)polate
-            (None, None) => None
+            (None, None) => Err(SampleError::Empty)
         }
     }
 }
 
+impl<T, V, F> DataSampler<T,V> for LerpSampler<F>
+where
+    F: Float,
+    T: Sub<Output = T> + Clone + PartialOrd + Into<F>,
+    V: Clone + Lerp<F> {
+    fn try_sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<V, SampleError>
+    where T: 'a, V: 'a {
+        let segment = self.segment(past_values, future_values, time)?;
+        Ok(segment.start.clone().lerp(segment.end.clone(), segment.ratio))
+    }
+}
+
+/// The aggregates and the threshold crossings follow the extrapolation policies
+impl<T, V, F> AggregateSampler<T,V> for LerpSampler<F>
+where
+    F: Float,
+    T: Sub<Output = T> + Clone + PartialOrd + Into<F>,
+    V: Clone + Lerp<F> {
+    fn interpolation(&self) -> Interpolation {
+        Interpolation::Linear
+    }
+
+    fn extrapolated_distance<G: Float>(&self, past: bool, (from, to): (G, G), ratio: G) -> G {
+        let policy = if past { self.past_extrapolation } else { self.future_extrapolation };
+        let cast = |distance: G| F::from(distance).unwrap_or_else(F::nan);
+        // the value is linear in the distance covered by the extrapolation
+        let (from, to) = (policy.apply(cast(from)), policy.apply(cast(to)));
+        match (from, to) {
+            (Ok(from), Ok(to)) => G::from(policy.distance(from + (to - from) * cast(ratio))).unwrap_or_else(G::nan),
+            _ => G::nan()
+        }
+    }
+
+    fn extrapolated_mean<G: Float>(&self, past: bool, (from, to): (G, G)) -> G {
+        let policy = if past { self.past_extrapolation } else { self.future_extrapolation };
+        let cast = |distance: G| F::from(distance).unwrap_or_else(F::nan);
+        let (from, to) = (cast(from), cast(to));
+        let half = F::one() / (F::one() + F::one());
+        let mean = match (policy.apply(from), policy.apply(to)) {
+            // a single instant, or a frozen value: any ratio gives the same value
+            (Ok(covered_from), Ok(covered_to)) if from == to || covered_from == covered_to => half,
+            (Ok(covered_from), Ok(covered_to)) => {
+                let mean_covered = (policy.covered_integral(to) - policy.covered_integral(from)) / (to - from);
+                (mean_covered - covered_from) / (covered_to - covered_from)
+            },
+            _ => F::nan()
+        };
+        G::from(mean).unwrap_or_else(G::nan)
+    }
+}
+
+/// The derivative is the slope of the segment used to inter/extrapolate,
+/// at a data key it is the slope of the segment before it (if any).
+/// The second derivative is zero, unless an extrapolation policy slows down the extrapolation.
+impl<T, V, F> DerivativeSampler<T,V> for LerpSampler<F>
+where
+    F: Float,
+    T: Sub<Output = T> + Clone + PartialOrd + Into<F>,
+    V: Clone + Lerp<F> + Slope<F>,
+    V::Output: Mul<F, Output = V::Output> {
+    type Derivative = V::Output;
+    fn try_sample_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a {
+        let segment = self.segment(past_values, future_values, time)?;
+        Ok(segment.start.clone().slope(segment.end.clone(), segment.duration) * segment.speed)
+    }
+    fn try_sample_second_derivative<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<Self::Derivative, SampleError>
+    where T: 'a, V: 'a {
+        let segment = self.segment(past_values, future_values, time)?;
+        Ok(segment.start.clone().slope(segment.end.clone(), segment.duration) * segment.acceleration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn keys<T,V>(input: &[(T,V)]) -> impl Iterator<Item = (&T,&V)> {
+        input.iter().map(|(t,v)| (t,v))
+    }
+
+    #[test]
+    fn extrapolation_policies() {
+        let past = vec![(4, 4.0), (0, 0.0)];
+        let future = vec![(0, 0.0), (4, 4.0)];
+        let sample = |sampler: &LerpSampler<f64>, past: &[(i32, f64)], future: &[(i32, f64)], time| sampler.try_sample(&mut keys(past), &mut keys(future), time);
+
+        let freeze = LerpSampler::builder().extrapolation(Extrapolation::Freeze(2.0)).build();
+        assert_eq!(sample(&freeze, &past, &[], 5), Ok(5.0));
+        assert_eq!(sample(&freeze, &past, &[], 10), Ok(6.0));
+        assert_eq!(sample(&freeze, &[], &future, -10), Ok(-2.0));
+
+        let decay = LerpSampler::builder().extrapolation(Extrapolation::Decay(4.0)).build();
+        assert_eq!(sample(&decay, &past, &[], 6), Ok(5.5));
+        assert_eq!(sample(&decay, &past, &[], 8), Ok(6.0));
+        assert_eq!(sample(&decay, &past, &[], 100), Ok(6.0));
+        assert_eq!(sample(&decay, &[], &future, -100), Ok(-2.0));
+
+        let one_sided = LerpSampler::builder()
+            .past_extrapolation(Extrapolation::Refuse)
+            .future_extrapolation(Extrapolation::Limit(3.0))
+            .build();
+        assert_eq!(sample(&one_sided, &past, &[], 7), Ok(7.0));
+        assert_eq!(sample(&one_sided, &past, &[], 8), Err(SampleError::OutOfRange));
+        assert_eq!(sample(&one_sided, &[], &future, 0), Ok(0.0));
+        assert_eq!(sample(&one_sided, &[], &future, -1), Err(SampleError::OutOfRange));
+        assert_eq!(sample(&one_sided, &past[1..], &future[1..], 2), Ok(2.0));
+    }
+
+    #[test]
+    fn derivatives() {
+        let past = vec![(4, 4.0), (0, 0.0)];
+        let future = vec![(8, 12.0)];
+        let sampler = LerpSampler::<f64>::builder().extrapolation(Extrapolation::Decay(4.0)).build();
+        let derivative = |past: &[(i32, f64)], future: &[(i32, f64)], time| sampler.try_sample_derivative(&mut keys(past), &mut keys(future), time);
+        let second_derivative = |past: &[(i32, f64)], future: &[(i32, f64)], time| sampler.try_sample_second_derivative(&mut keys(past), &mut keys(future), time);
+        assert_eq!(derivative(&past, &future, 6), Ok(2.0));
+        assert_eq!(second_derivative(&past, &future, 6), Ok(0.0));
+        assert_eq!(derivative(&past, &[], 6), Ok(0.5));
+        assert_eq!(second_derivative(&past, &[], 6), Ok(-0.25));
+        assert_eq!(derivative(&past, &[], 10), Ok(0.0));
+        assert_eq!(derivative(&[], &[(0, 0.0), (4, 4.0)], -2), Ok(0.5));
+        assert_eq!(second_derivative(&[], &[(0, 0.0), (4, 4.0)], -2), Ok(0.25));
+        assert_eq!(derivative(&[], &[(0, 0.0)], -2), Err(SampleError::NotEnoughKeys));
+    }
+}
+
 #[cfg(feature = "nalgebra_impl")]
 #[cfg(test)]
 mod tests_nalgebra {
@@ -130,86 +430,129 @@ mod tests_nalgebra {
             .map(|p|(p.0, Vector1::new(p.1)))
             .collect()
     }
+    fn keys<T,V>(input: &[(T,V)]) -> impl Iterator<Item = (&T,&V)> {
+        input.iter().map(|(t,v)| (t,v))
+    }
 
     #[test]
     fn interpolation_success() {
         let past = adapt(vec![(15_i32, 20_i32), (10, 1)]);
         let future = adapt(vec![(20_i32, 30_i32), (25, 4)]);
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 17), Some(Vector1::new(24)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15), Some(Vector1::new(20)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20), Some(Vector1::new(30)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 17), Some(Vector1::new(24)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15), Some(Vector1::new(20)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20), Some(Vector1::new(30)));
 
     }
     #[test]
     fn interpolation_success_i32_f32() {
         let past = adapt(vec![(15, 20.26_f32), (10, 1.0)]);
         let future = adapt(vec![(20, 30_f32), (25, 4.0)]);
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 18), Some(Vector1::new(26.104)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15), Some(Vector1::new(20.26)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20), Some(Vector1::new(30.0)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 18), Some(Vector1::new(26.104)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15), Some(Vector1::new(20.26)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20), Some(Vector1::new(30.0)));
 
     }
     #[test]
     fn interpolation_success_i32_f64() {
         let past = adapt(vec![(15, 20.26_f64), (10, 1.0)]);
         let future = adapt(vec![(20, 30_f64), (25, 4.0)]);
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 18), Some(Vector1::new(26.104)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15), Some(Vector1::new(20.26)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20), Some(Vector1::new(30.0)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 18), Some(Vector1::new(26.104)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15), Some(Vector1::new(20.26)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20), Some(Vector1::new(30.0)));
 
     }
     #[test]
     fn interpolation_success_f32_i32() {
         let past = adapt(vec![(15_f32, 20), (10.0, 1)]);
         let future = adapt(vec![(20_f32, 30), (25.0, 4)]);
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 18.0), Some(Vector1::new(26)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15.0), Some(Vector1::new(20)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20.0), Some(Vector1::new(30)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 18.0), Some(Vector1::new(26)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15.0), Some(Vector1::new(20)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20.0), Some(Vector1::new(30)));
     }
     #[test]
     fn interpolation_success_f64_i32() {
         let past = adapt(vec![(15_f64, 20), (10.0, 1)]);
         let future = adapt(vec![(20_f64, 30), (25.0, 4)]);
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 18.0), Some(Vector1::new(26)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15.0), Some(Vector1::new(20)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20.0), Some(Vector1::new(30)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 18.0), Some(Vector1::new(26)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15.0), Some(Vector1::new(20)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20.0), Some(Vector1::new(30)));
     }
     
     #[test]
     fn interpolation_failed() {
         let past: Vec<(i32,Vector1<i32>)> = Vec::new();
         let future = Vec::new();
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 42), None);
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 42), None);
 
     }
     #[test]
     fn extrapolation_future_success() {
         let past = adapt(vec![(15, 2), (10, 1)]);
         let future = Vec::new();
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15), Some(Vector1::new(2)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 20), Some(Vector1::new(3)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15), Some(Vector1::new(2)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 20), Some(Vector1::new(3)));
 
     }
     #[test]
     fn extrapolation_future_failed() {
         let past = adapt(vec![(10, 1)]);
         let future = Vec::new();
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 15), None);
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 15), None);
 
     }
     #[test]
     fn extrapolation_past_success() {
-        let future = adapt(vec![(15, 2), (10, 1)]);
+        let future = adapt(vec![(10, 1), (15, 2)]);
         let past = Vec::new();
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 10), Some(Vector1::new(1)));
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 5), Some(Vector1::new(0)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 10), Some(Vector1::new(1)));
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 5), Some(Vector1::new(0)));
 
     }
     #[test]
+    fn extrapolation_past_unordered() {
+        let future = adapt(vec![(15, 2), (10, 1)]);
+        let past = Vec::new();
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&past), &mut keys(&future), 10), Err(SampleError::OutOfOrder));
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&past), &mut keys(&future), 5), Err(SampleError::OutOfOrder));
+    }
+    #[test]
     fn extrapolation_past_failed() {
         let future = adapt(vec![(10, 1)]);
         let past = Vec::new();
-        assert_eq!(LerpSampler::<f64>::sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 5), None);
+        assert_eq!(LerpSampler::<f64>::new().sample(&mut keys(&past), &mut keys(&future), 5), None);
 
     }
+    #[test]
+    fn derivative_vector() {
+        let past = adapt(vec![(15, 20), (10, 1)]);
+        let future = adapt(vec![(20, 30)]);
+        assert_eq!(LerpSampler::<f64>::new().try_sample_derivative(&mut keys(&past), &mut keys(&future), 17), Ok(Vector1::new(2.0)));
+        assert_eq!(LerpSampler::<f64>::new().try_sample_second_derivative(&mut keys(&past), &mut keys(&future), 17), Ok(Vector1::new(0.0)));
+    }
+    #[test]
+    fn sample_errors() {
+        let empty: Vec<(i32,Vector1<i32>)> = Vec::new();
+        let single = adapt(vec![(10, 1)]);
+        let duplicated = adapt(vec![(10, 1), (10, 2)]);
+        let unordered = adapt(vec![(10, 1), (15, 2)]);
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&empty), &mut keys(&empty), 5), Err(SampleError::Empty));
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&single), &mut keys(&empty), 15), Err(SampleError::NotEnoughKeys));
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&empty), &mut keys(&duplicated), 5), Err(SampleError::ZeroLengthSegment));
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&unordered), &mut keys(&empty), 20), Err(SampleError::OutOfOrder));
+        assert_eq!(LerpSampler::<f64>::new().try_sample(&mut keys(&single), &mut keys(&single), 5), Err(SampleError::OutOfOrder));
+    }
+    #[test]
+    fn extrapolation_bounded() {
+        let past = adapt(vec![(15, 2), (10, 1)]);
+        let future = Vec::new();
+        let sampler = LerpSampler::<f64>::builder().max_extrapolation(5.0).build();
+        assert_eq!(sampler.try_sample(&mut keys(&past), &mut keys(&future), 20), Ok(Vector1::new(3)));
+        assert_eq!(sampler.try_sample(&mut keys(&past), &mut keys(&future), 21), Err(SampleError::OutOfRange));
+        let ordered = adapt(vec![(10, 1), (15, 2)]);
+        assert_eq!(sampler.try_sample(&mut keys(&future), &mut keys(&ordered), 4), Err(SampleError::OutOfRange));
+        let sampler = LerpSampler::<f64>::builder().extrapolate(false).build();
+        assert_eq!(sampler.try_sample(&mut keys(&past), &mut keys(&future), 15), Ok(Vector1::new(2)));
+        assert_eq!(sampler.try_sample(&mut keys(&past), &mut keys(&future), 16), Err(SampleError::OutOfRange));
+        assert_eq!(sampler.try_sample(&mut keys(&past[1..]), &mut keys(&past[..1]), 12), Ok(Vector1::new(1)));
+    }
 }
\ No newline at end of file
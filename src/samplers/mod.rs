@@ -3,8 +3,12 @@
  */
 mod step;
 mod lerp;
-//mod cubic;
+mod slope;
+mod cubic;
+#[cfg(feature = "nalgebra_impl")]
+mod slerp;
 
-pub use self::step::StepSampler;
-pub use self::lerp::{LerpSampler, Lerp};
-//pub use self::cubic::CubicSampler;
\ No newline at end of file
+pub use self::step::{StepSampler, StepSamplerBuilder};
+pub use self::lerp::{LerpSampler, LerpSamplerBuilder, Extrapolation, Lerp};
+pub use self::slope::Slope;
+pub use self::cubic::CubicSampler;
\ No newline at end of file
@@ -0,0 +1,116 @@
+/*!
+ * Spherical interpolation of nalgebra rotations and isometries
+ *
+ * The memberwise [Lerp](trait.Lerp.html) of matrices does not keep rotations unitary,
+ * those implementations rotate at constant angular speed around a single axis instead (slerp).
+ * The rotation from one key to the other is always the shortest one (less than half a turn),
+ * so a quaternion and its opposite are interpolated the same way.
+ */
+use crate::samplers::Lerp;
+use nalgebra::{UnitQuaternion, UnitComplex, Rotation2, Rotation3, Isometry2, Isometry3, Translation};
+
+macro_rules! impl_slerp {
+    ($p:ty, $n:ty) => {
+        impl Lerp<$p> for UnitQuaternion<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                self.rotation_to(&other).powf(t as $n) * self
+            }
+        }
+
+        impl Lerp<$p> for UnitComplex<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                self.rotation_to(&other).powf(t as $n) * self
+            }
+        }
+
+        impl Lerp<$p> for Rotation2<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                self.rotation_to(&other).powf(t as $n) * self
+            }
+        }
+
+        impl Lerp<$p> for Rotation3<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                self.rotation_to(&other).powf(t as $n) * self
+            }
+        }
+
+        /// The translation is interpolated linearly and the rotation spherically
+        impl Lerp<$p> for Isometry2<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                Isometry2::from_parts(
+                    Translation::from(self.translation.vector.lerp(other.translation.vector, t)),
+                    self.rotation.lerp(other.rotation, t))
+            }
+        }
+
+        /// The translation is interpolated linearly and the rotation spherically
+        impl Lerp<$p> for Isometry3<$n> {
+            fn lerp(self, other: Self, t: $p) -> Self {
+                Isometry3::from_parts(
+                    Translation::from(self.translation.vector.lerp(other.translation.vector, t)),
+                    self.rotation.lerp(other.rotation, t))
+            }
+        }
+    };
+}
+
+impl_slerp!(f32, f32);
+impl_slerp!(f32, f64);
+impl_slerp!(f64, f32);
+impl_slerp!(f64, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::TimeLine;
+    use crate::timelines::LerpBtreeTimeline;
+    use nalgebra::{Vector3, Quaternion};
+    use std::collections::BTreeMap;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn quaternion_constant_speed() {
+        let start = UnitQuaternion::identity();
+        let end = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI / 2.0);
+        let quarter = start.lerp(end, 0.25_f64);
+        assert!((quarter.angle() - PI / 8.0).abs() < 1e-9);
+        assert!((quarter.quaternion().norm() - 1.0).abs() < 1e-9);
+        let extrapolated = start.lerp(end, 1.5_f64);
+        assert!(extrapolated.angle_to(&UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 3.0 * PI / 4.0)) < 1e-9);
+    }
+
+    #[test]
+    fn quaternion_shortest_path() {
+        let start = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.1);
+        let end = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.3);
+        let opposite_end = UnitQuaternion::new_unchecked(-end.into_inner());
+        let middle = start.lerp(opposite_end, 0.5_f64);
+        assert!(middle.angle_to(&UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.2)) < 1e-9);
+        assert!(UnitQuaternion::new_unchecked(Quaternion::new(1.0, 0.0, 0.0, 0.0)).lerp(opposite_end, 0.0_f64).angle() < 1e-9);
+    }
+
+    #[test]
+    fn complex_and_rotation() {
+        let middle = UnitComplex::new(0.5_f32).lerp(UnitComplex::new(1.5), 0.5_f64);
+        assert!((middle.angle() - 1.0).abs() < 1e-6);
+        let middle = Rotation2::new(3.0_f64).lerp(Rotation2::new(-3.0), 0.5_f64);
+        assert!((middle.angle().abs() - PI).abs() < 1e-9);
+        let end = Rotation3::from_axis_angle(&Vector3::x_axis(), 1.0);
+        let middle = Rotation3::identity().lerp(end, 0.5_f64);
+        assert!(middle.angle_to(&Rotation3::from_axis_angle(&Vector3::x_axis(), 0.5)) < 1e-9);
+    }
+
+    #[test]
+    fn isometry_timeline() {
+        let start = Isometry3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let end = Isometry3::new(Vector3::new(2.0, 4.0, 0.0), Vector3::new(0.0, PI / 2.0, 0.0));
+        let mut data = BTreeMap::new();
+        data.insert(0, start);
+        data.insert(10, end);
+        let timeline = LerpBtreeTimeline::<_,_,f64>::new(data);
+        let middle = timeline.get_sample(5).unwrap();
+        assert_eq!(middle.translation.vector, Vector3::new(1.0, 2.0, 0.0));
+        assert!(middle.rotation.angle_to(&UnitQuaternion::from_axis_angle(&Vector3::y_axis(), PI / 4.0)) < 1e-9);
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "nalgebra_impl")]
+use nalgebra::{MatrixMN, Dim, Scalar, allocator::Allocator, DefaultAllocator};
+#[cfg(feature = "nalgebra_impl")]
+use num_traits::Float;
+
+/// Items whose rate of change can be measured acording to float type F
+pub trait Slope<F> {
+    /// Type of the rate of change (ex: velocity for a position)
+    type Output;
+    /// rate of change needed to go from self to other in `duration`
+    /// (other - self) / duration
+    fn slope(self, other: Self, duration: F) -> Self::Output;
+}
+
+#[cfg(feature = "nalgebra_impl")]
+/// Implement Slope for nalgebra matrixMN (and VectorN) type.
+/// The rate of change is computed memberwise for each matrix element,
+/// so the rate of change of a vector of integers is a vector of F
+impl<N, R, C, F> Slope<F> for MatrixMN<N, R, C>
+where
+    N: Scalar + Slope<F, Output = F>,
+    F: Float + Scalar,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: Allocator<N, R, C> + Allocator<F, R, C> {
+    type Output = MatrixMN<F, R, C>;
+    fn slope(self, other: Self, duration: F) -> Self::Output {
+        self.zip_map(&other, |s, o| s.slope(o, duration))
+    }
+}
+
+macro_rules! impl_slope {
+    ($p:ty, $t:ty) => {
+        impl Slope<$p> for $t {
+            type Output = $p;
+            fn slope(self, other: Self, duration: $p) -> $p {
+                (other as $p - self as $p) / duration
+            }
+        }
+    };
+}
+
+impl_slope!(f32, usize);
+impl_slope!(f32, isize);
+impl_slope!(f32, u8);
+impl_slope!(f32, i8);
+impl_slope!(f32, u16);
+impl_slope!(f32, i16);
+impl_slope!(f32, u32);
+impl_slope!(f32, i32);
+impl_slope!(f32, f32);
+impl_slope!(f32, u64);
+impl_slope!(f32, i64);
+impl_slope!(f32, f64);
+impl_slope!(f32, u128);
+impl_slope!(f32, i128);
+
+impl_slope!(f64, usize);
+impl_slope!(f64, isize);
+impl_slope!(f64, u8);
+impl_slope!(f64, i8);
+impl_slope!(f64, u16);
+impl_slope!(f64, i16);
+impl_slope!(f64, u32);
+impl_slope!(f64, i32);
+impl_slope!(f64, f32);
+impl_slope!(f64, u64);
+impl_slope!(f64, i64);
+impl_slope!(f64, f64);
+impl_slope!(f64, u128);
+impl_slope!(f64, i128);
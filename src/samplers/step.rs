@@ -1,31 +1,142 @@
-use crate::interface::DataSampler;
+use crate::interface::{DataSampler, AggregateSampler, Interpolation, SampleError};
+use num_traits::Zero;
+use std::ops::Sub;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 
 /// Implement StepSampler for last velue strategy
 /// 
 /// This sampler only return the last value of the data relative to intant t
 /// > Note: since the generation of past_values and future_values iterators 
-/// has already be done by the [DataContainer](../interface/trait.DataContainer.html)
-/// the variables `future_values` and `t` are note used and the first value of 
-/// `past_values` is simply returned
-#[allow(dead_code)]
-pub struct StepSampler;
+/// > has already be done by the [DataContainer](../interface/trait.DataContainer.html)
+/// > the first value of `past_values` is simply returned, unless the first value of
+/// > `future_values` is exactly at instant t
+/// 
+/// By default the last value is held forever after the last data key, use
+/// [builder](struct.StepSampler.html#method.builder) to limit it
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StepSampler<T> {
+    max_extrapolation: Option<T>
+}
+
+impl<T> StepSampler<T> {
+    /// Sampler holding the last value forever
+    pub fn new() -> Self {
+        StepSampler {
+            max_extrapolation: None
+        }
+    }
 
-impl<T,V> DataSampler<T,V> for StepSampler {
-    fn sample(past_values: &mut Iterator<Item = (T,V)>, _: &mut Iterator<Item = (T,V)>, _: T) -> Option<V> {
-        Some(past_values.next()?.1)
+    pub fn builder() -> StepSamplerBuilder<T> {
+        StepSamplerBuilder {
+            sampler: StepSampler::new()
+        }
+    }
+
+    /// Maximum time the last value is held after the last data key, if the extrapolation is bounded
+    pub fn max_extrapolation(&self) -> Option<&T> {
+        self.max_extrapolation.as_ref()
     }
 }
 
+impl<T> Default for StepSampler<T> {
+    fn default() -> Self {
+        StepSampler::new()
+    }
+}
+
+/// Builder of a configured [StepSampler](struct.StepSampler.html)
+pub struct StepSamplerBuilder<T> {
+    sampler: StepSampler<T>
+}
+
+impl<T: Zero> StepSamplerBuilder<T> {
+    /// Hold (default) or not the last value after the last data key
+    pub fn extrapolate(mut self, extrapolate: bool) -> Self {
+        self.sampler.max_extrapolation = if extrapolate { None } else { Some(T::zero()) };
+        self
+    }
+
+    /// Hold the last value at most `duration` after the last data key
+    pub fn max_extrapolation(mut self, duration: T) -> Self {
+        self.sampler.max_extrapolation = Some(duration);
+        self
+    }
+
+    pub fn build(self) -> StepSampler<T> {
+        self.sampler
+    }
+}
+
+impl<T,V> DataSampler<T,V> for StepSampler<T>
+where
+    T: Sub<Output = T> + Clone + PartialOrd,
+    V: Clone {
+    fn try_sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, future_values: &mut dyn Iterator<Item = (&'a T, &'a V)>, time: T) -> Result<V, SampleError>
+    where T: 'a, V: 'a {
+        match (past_values.next(), future_values.next()) {
+            (_, Some((ft, fv))) if *ft == time => Ok(fv.clone()),
+            (Some((pt, pv)), future) => {
+                SampleError::check_order(pt, &time)?;
+                match (future, &self.max_extrapolation) {
+                    //After the last data key
+                    (None, Some(max)) if time - pt.clone() > *max => Err(SampleError::OutOfRange),
+                    _ => Ok(pv.clone())
+                }
+            },
+            //No value before time
+            (None, Some(_)) => Err(SampleError::NotEnoughKeys),
+            (None, None) => Err(SampleError::Empty)
+        }
+    }
+}
+
+impl<T,V> AggregateSampler<T,V> for StepSampler<T>
+where
+    T: Sub<Output = T> + Clone + PartialOrd,
+    V: Clone {
+    fn interpolation(&self) -> Interpolation {
+        Interpolation::Step
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn it_works() {
-        let mut past = vec![(15_i32, 2_i32), (10, 1)].into_iter();
-        let mut future = vec![(20_i32, 3_i32), (25, 4)].into_iter();
-        let sample = StepSampler::sample(&mut past, &mut future, 42_i32);
+        let mut past = vec![(&15_i32, &2_i32), (&10, &1)].into_iter();
+        let mut future = vec![(&20_i32, &3_i32), (&25, &4)].into_iter();
+        let sample = StepSampler::new().sample(&mut past, &mut future, 42_i32);
         assert_eq!(sample, Some(2));
     }
+    #[test]
+    fn exact_key() {
+        let mut past = vec![(&15_i32, &2_i32), (&10, &1)].into_iter();
+        let mut future = vec![(&20_i32, &3_i32), (&25, &4)].into_iter();
+        let sample = StepSampler::new().sample(&mut past, &mut future, 20_i32);
+        assert_eq!(sample, Some(3));
+    }
+    #[test]
+    fn sample_errors() {
+        let mut past = Vec::<(&i32, &i32)>::new().into_iter();
+        let mut future = vec![(&20_i32, &3_i32)].into_iter();
+        assert_eq!(StepSampler::new().try_sample(&mut past, &mut future, 15), Err(SampleError::NotEnoughKeys));
+        let mut past = vec![(&15_i32, &2_i32)].into_iter();
+        assert_eq!(StepSampler::new().try_sample(&mut past, &mut Vec::new().into_iter(), 10), Err(SampleError::OutOfOrder));
+        assert_eq!(StepSampler::new().try_sample(&mut Vec::<(&i32, &i32)>::new().into_iter(), &mut Vec::new().into_iter(), 10), Err(SampleError::Empty));
+    }
+    #[test]
+    fn extrapolation_bounded() {
+        let sampler = StepSampler::builder().max_extrapolation(5).build();
+        let past = vec![(&15_i32, &2_i32), (&10, &1)];
+        let future = vec![(&20_i32, &3_i32)];
+        assert_eq!(sampler.try_sample(&mut past.clone().into_iter(), &mut Vec::new().into_iter(), 20), Ok(2));
+        assert_eq!(sampler.try_sample(&mut past.clone().into_iter(), &mut Vec::new().into_iter(), 21), Err(SampleError::OutOfRange));
+        let sampler = StepSampler::builder().extrapolate(false).build();
+        assert_eq!(sampler.try_sample(&mut past.clone().into_iter(), &mut future.clone().into_iter(), 19), Ok(2));
+        assert_eq!(sampler.try_sample(&mut past.clone().into_iter(), &mut Vec::new().into_iter(), 16), Err(SampleError::OutOfRange));
+    }
 }
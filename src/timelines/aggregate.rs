@@ -0,0 +1,141 @@
+use crate::interface::{AggregateContainer, AggregateSampler, Aggregate, Interpolation, SampleError, duration};
+use num_traits::{Float, ToPrimitive};
+use std::ops::{RangeInclusive, Sub};
+use super::Timeline;
+
+impl<C,S,T,V> Timeline<C,S,T,V>
+where
+    C: AggregateContainer<T,V>,
+    S: AggregateSampler<T,V>,
+    T: Clone + PartialOrd + Sub<Output = T> + ToPrimitive,
+    V: Float {
+    /// Minimum, maximum, integral and mean (time-weighted) of the value over `range`, without resampling
+    /// (ex: the max altitude between 2 instants)
+    ///
+    /// The aggregates follow the curve of the sampler (ex: [StepSampler](../samplers/struct.StepSampler.html)
+    /// or [LerpSampler](../samplers/struct.LerpSampler.html)) from the sample at the start of the range,
+    /// through the data keys in the range, to the sample at the end of the range.
+    /// Fails if the start or the end can't be sampled, or if the range is reversed
+    pub fn try_aggregate(&self, range: RangeInclusive<T>) -> Result<Aggregate<T,V>, SampleError> {
+        let (start, end) = range.into_inner();
+        SampleError::check_order(&start, &end)?;
+        let interpolation = self.sampler().interpolation();
+        let sample = |time: &T| {
+            let (mut past, mut future) = self.split_at(time);
+            self.sampler().try_sample(&mut past, &mut future, time.clone())
+        };
+        let first = Aggregate::point(start.clone(), sample(&start)?);
+        let last = Aggregate::point(end.clone(), sample(&end)?);
+        let (first_key, last_key) = self.extrapolation_keys(&start, &end);
+        // the integral between 2 points, following the extrapolation curve before the first data key and after the last one
+        let join = |from: &Aggregate<T,V>, to: &Aggregate<T,V>| {
+            let ((from, from_value), (to, to_value)) = (&from.last, &to.first);
+            let mean = match (interpolation, first_key, last_key) {
+                (Interpolation::Linear, Some(first_key), _) if to <= first_key =>
+                    self.sampler().extrapolated_mean(true, (duration(from, first_key), duration(to, first_key))),
+                (Interpolation::Linear, _, Some(last_key)) if from >= last_key =>
+                    self.sampler().extrapolated_mean(false, (duration(last_key, from), duration(last_key, to))),
+                _ => return interpolation.integral((from, from_value), (to, to_value))
+            };
+            duration::<T,V>(from, to) * (*from_value + (*to_value - *from_value) * mean)
+        };
+        Ok(match self.aggregate_keys(&start, &end, interpolation) {
+            Some(keys) => {
+                let (first_join, last_join) = (join(&first, &keys), join(&keys, &last));
+                first.joined(keys, first_join).joined(last, last_join)
+            },
+            None => {
+                let join = join(&first, &last);
+                first.joined(last, join)
+            }
+        })
+    }
+
+    /// Same as `try_aggregate` but returns None on failure
+    pub fn aggregate(&self, range: RangeInclusive<T>) -> Option<Aggregate<T,V>> {
+        self.try_aggregate(range).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::containers::AggregateVec;
+    use crate::interface::{Aggregate, SampleError};
+    use crate::samplers::{LerpSampler, StepSampler};
+    use crate::timelines::{Timeline, LerpBtreeTimeline, StepBtreeTimeline};
+
+    const ALTITUDE: [(u32, f64); 5] = [(0, 0.0), (10, 100.0), (20, 50.0), (30, 80.0), (40, 0.0)];
+
+    #[test]
+    fn linear() {
+        let timeline = LerpBtreeTimeline::<u32, f64, f64>::new(ALTITUDE.iter().cloned().collect());
+        let aggregate = timeline.try_aggregate(5..=25).unwrap();
+        assert_eq!(aggregate, Aggregate { first: (5, 50.0), last: (25, 65.0), min: 50.0, max: 100.0, integral: 1412.5 });
+        assert_eq!(aggregate.mean(), 70.625);
+        assert_eq!(timeline.try_aggregate(12..=12).unwrap().mean(), 90.0);
+        // extrapolated from the first 2 data keys
+        assert_eq!(timeline.try_aggregate(0..=50).unwrap().integral, 1900.0);
+        let (start, end) = (5, 4);
+        assert_eq!(timeline.try_aggregate(start..=end), Err(SampleError::OutOfOrder));
+    }
+
+    #[test]
+    fn extrapolation_policies() {
+        use crate::samplers::Extrapolation;
+        let keys: std::collections::BTreeMap<i32, f64> = vec![(0, 0.0), (10, 10.0)].into_iter().collect();
+        let freeze = Timeline::with_sampler(keys.clone(), LerpSampler::builder().extrapolation(Extrapolation::Freeze(5.0)).build());
+        // up to 15 at 15, then 15 until 30
+        let aggregate = freeze.try_aggregate(0..=30).unwrap();
+        assert_eq!((aggregate.min, aggregate.max, aggregate.integral), (0.0, 15.0, 50.0 + 62.5 + 225.0));
+        // no data key in the range
+        assert_eq!(freeze.try_aggregate(12..=30).unwrap().integral, 40.5 + 225.0);
+        // down to -5 at -5, then -5 until -20
+        assert_eq!(freeze.try_aggregate(-20..=0).unwrap().integral, -12.5 - 75.0);
+
+        let decay = Timeline::with_sampler(keys, LerpSampler::builder().future_extrapolation(Extrapolation::Decay(10.0)).build());
+        // 10 + d - d²/20 up to 15 at 20, then 15
+        let aggregate = decay.try_aggregate(10..=30).unwrap();
+        assert_eq!(aggregate.max, 15.0);
+        assert!((aggregate.integral - (200.0 + 100.0 / 3.0 + 50.0)).abs() < 1e-9);
+        let aggregate = decay.try_aggregate(15..=25).unwrap();
+        assert!((aggregate.integral - (50.0 + 37.5 - 875.0 / 60.0 + 75.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn step() {
+        let timeline = StepBtreeTimeline::<u32, f64>::new(ALTITUDE.iter().cloned().collect());
+        let aggregate = timeline.try_aggregate(5..=30).unwrap();
+        assert_eq!((aggregate.min, aggregate.max, aggregate.integral), (0.0, 100.0, 1500.0));
+        assert_eq!(aggregate.mean(), 60.0);
+        assert_eq!(timeline.try_aggregate(40..=45).unwrap().integral, 0.0);
+        let bounded = Timeline::with_sampler(timeline.into_inner(), StepSampler::builder().max_extrapolation(5).build());
+        assert_eq!(bounded.try_aggregate(0..=46), Err(SampleError::OutOfRange));
+    }
+
+    #[test]
+    fn time_and_value_types() {
+        let step = StepBtreeTimeline::<u64, f32>::new(vec![(0, 1.0), (10, 3.0)].into_iter().collect());
+        assert_eq!(step.try_aggregate(0..=20).unwrap().mean(), 2.0);
+        let lerp = LerpBtreeTimeline::<u32, f32, f64>::new(vec![(0, 1.0), (10, 3.0)].into_iter().collect());
+        assert_eq!(lerp.try_aggregate(0..=10).unwrap().integral, 20.0);
+        let augmented: Timeline<AggregateVec<i64, f64>, StepSampler<i64>, i64, f64> = Timeline::new(vec![(0, 1.0), (10, 3.0)].into_iter().collect());
+        assert_eq!(augmented.try_aggregate(0..=20).unwrap().integral, 40.0);
+    }
+
+    #[test]
+    fn augmented_container() {
+        let keys = (0..200u32).map(|t| (t * 3, f64::from((t * 37) % 101) - 50.0));
+        let plain = LerpBtreeTimeline::<u32, f64, f64>::new(keys.clone().collect());
+        let augmented: Timeline<AggregateVec<u32, f64>, LerpSampler, u32, f64> = Timeline::new(keys.clone().collect());
+        let step: Timeline<AggregateVec<u32, f64>, StepSampler<u32>, u32, f64> = Timeline::new(keys.collect());
+        let plain_step = StepBtreeTimeline::<u32, f64>::new(plain.iter().map(|(t, v)| (*t, *v)).collect());
+        for (start, end) in [(0, 597), (1, 2), (3, 3), (4, 300), (100, 101), (250, 590)] {
+            let (expected, aggregate) = (plain.try_aggregate(start..=end).unwrap(), augmented.try_aggregate(start..=end).unwrap());
+            assert_eq!((aggregate.min, aggregate.max), (expected.min, expected.max));
+            assert!((aggregate.integral - expected.integral).abs() < 1e-6);
+            let (expected, aggregate) = (plain_step.try_aggregate(start..=end).unwrap(), step.try_aggregate(start..=end).unwrap());
+            assert_eq!((aggregate.min, aggregate.max), (expected.min, expected.max));
+            assert!((aggregate.integral - expected.integral).abs() < 1e-6);
+        }
+    }
+}
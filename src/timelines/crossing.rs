@@ -0,0 +1,258 @@
+use crate::interface::{TimeLine, DataContainer, DataContainerMut, AggregateSampler, Interpolation, SampleError, duration};
+use crate::samplers::Lerp;
+use num_traits::{Float, ToPrimitive};
+use std::ops::{RangeInclusive, Sub};
+use super::Timeline;
+
+/// Direction of a [Crossing](struct.Crossing.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// The value goes up to the threshold, or the predicate becomes true
+    Rising,
+    /// The value goes down below the threshold, or the predicate becomes false
+    Falling
+}
+
+/// Instant at which a timeline crosses a threshold (or changes a predicate), in time units converted to F
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossing<F> {
+    pub time: F,
+    pub edge: Edge
+}
+
+/// A Timeline that can find the instants its value crosses a threshold (or changes a predicate)
+///
+/// Implemented by [Timeline](struct.Timeline.html) with an [AggregateSampler](../interface/trait.AggregateSampler.html),
+/// and forwarded by the adapters ([RelativeNow](struct.RelativeNow.html) gives the instants relative to now).
+///
+/// T: the type that encode time (ex: u32)
+pub trait CrossingTimeLine<T: Clone>: TimeLine<T> {
+    /// Instants in `range` at which the value crosses `threshold` (ex: health crossing 0), oldest first
+    ///
+    /// The value is above the threshold when it is greater or equal: a rising edge is the first instant
+    /// at or above the threshold, a falling edge is the last instant at the threshold before going below it.
+    /// The instants are exact for linear and step interpolation ([AggregateSampler](../interface/trait.AggregateSampler.html)),
+    /// including the extrapolation policies of the [LerpSampler](../samplers/struct.LerpSampler.html).
+    /// Fails if the start or the end of the range can't be sampled, or if the range is reversed
+    fn try_crossings(&self, range: RangeInclusive<T>, threshold: Self::Item) -> Result<Vec<Crossing<Self::Item>>, SampleError>
+    where Self::Item: Float;
+
+    /// Same as `try_crossings` but returns None on failure
+    fn crossings(&self, range: RangeInclusive<T>, threshold: Self::Item) -> Option<Vec<Crossing<Self::Item>>>
+    where Self::Item: Float {
+        self.try_crossings(range, threshold).ok()
+    }
+
+    /// Instants in `range` at which `predicate` on the value changes (ex: the distance to a target drops below 5), oldest first
+    ///
+    /// A rising edge is the first instant the predicate is true, a falling edge the first instant it is false.
+    /// With step interpolation the instants are the data keys, with linear interpolation they are found
+    /// by bisection on the segment, up to the precision of F.
+    /// A predicate that changes and changes back between 2 data keys is not detected
+    fn try_edges<F, P>(&self, range: RangeInclusive<T>, predicate: P) -> Result<Vec<Crossing<F>>, SampleError>
+    where
+        Self::Item: Clone + Lerp<F>,
+        F: Float,
+        P: FnMut(&Self::Item) -> bool;
+
+    /// Same as `try_edges` but returns None on failure
+    fn edges<F, P>(&self, range: RangeInclusive<T>, predicate: P) -> Option<Vec<Crossing<F>>>
+    where
+        Self::Item: Clone + Lerp<F>,
+        F: Float,
+        P: FnMut(&Self::Item) -> bool {
+        self.try_edges(range, predicate).ok()
+    }
+}
+
+impl<C,S,T,V> CrossingTimeLine<T> for Timeline<C,S,T,V>
+where
+    C: DataContainer<T,V> + DataContainerMut<T,V>,
+    S: AggregateSampler<T,V>,
+    T: Clone + PartialOrd + Sub<Output = T> + ToPrimitive,
+    V: Clone {
+    fn try_crossings(&self, range: RangeInclusive<T>, threshold: V) -> Result<Vec<Crossing<V>>, SampleError>
+    where V: Float {
+        let interpolation = self.sampler().interpolation();
+        self.walk(range, |(_, start_value), (_, end_value)| {
+            let edge = match (*start_value >= threshold, *end_value >= threshold) {
+                (false, true) => Edge::Rising,
+                (true, false) => Edge::Falling,
+                _ => return None
+            };
+            let ratio = match interpolation {
+                Interpolation::Step => V::one(),
+                Interpolation::Linear => (threshold - *start_value) / (*end_value - *start_value)
+            };
+            Some((ratio, edge))
+        })
+    }
+
+    fn try_edges<F, P>(&self, range: RangeInclusive<T>, mut predicate: P) -> Result<Vec<Crossing<F>>, SampleError>
+    where
+        V: Clone + Lerp<F>,
+        F: Float,
+        P: FnMut(&V) -> bool {
+        let interpolation = self.sampler().interpolation();
+        self.walk(range, |(_, start_value), (_, end_value)| {
+            let state = predicate(start_value);
+            if predicate(end_value) == state {
+                return None;
+            }
+            let edge = if state { Edge::Falling } else { Edge::Rising };
+            let ratio = match interpolation {
+                Interpolation::Step => F::one(),
+                Interpolation::Linear => {
+                    let (mut before, mut after) = (F::zero(), F::one());
+                    let half = F::one() / (F::one() + F::one());
+                    loop {
+                        let middle = (before + after) * half;
+                        if middle <= before || middle >= after {
+                            break;
+                        }
+                        if predicate(&start_value.clone().lerp(end_value.clone(), middle)) == state {
+                            before = middle;
+                        } else {
+                            after = middle;
+                        }
+                    }
+                    after
+                }
+            };
+            Some((ratio, edge))
+        })
+    }
+}
+
+impl<C,S,T,V> Timeline<C,S,T,V>
+where
+    C: DataContainer<T,V>,
+    S: AggregateSampler<T,V>,
+    T: Clone + PartialOrd + Sub<Output = T> + ToPrimitive {
+    /// Check each segment of the curve in `range`, from the sample at the start through the data keys to the sample at the end
+    ///
+    /// `segment` gives where the crossing is between the values at the ends of the segment (0 to 1, 1 for a step),
+    /// converted to an instant along the extrapolation curve before the first data key and after the last one
+    fn walk<F, G>(&self, range: RangeInclusive<T>, mut segment: G) -> Result<Vec<Crossing<F>>, SampleError>
+    where
+        F: Float,
+        G: FnMut((&T, &V), (&T, &V)) -> Option<(F, Edge)> {
+        let (start, end) = range.into_inner();
+        SampleError::check_order(&start, &end)?;
+        let sample = |time: &T| {
+            let (mut past, mut future) = self.split_at(time);
+            self.sampler().try_sample(&mut past, &mut future, time.clone())
+        };
+        let (first, last) = (sample(&start)?, sample(&end)?);
+        let (first_key, last_key) = self.extrapolation_keys(&start, &end);
+        let (_, future) = self.split_at(&start);
+        let keys = future.skip_while(|(time, _)| **time <= start).take_while(|(time, _)| **time < end);
+
+        let float = |time: &T| F::from(time.clone()).unwrap_or_else(F::nan);
+        let mut crossings = Vec::new();
+        let mut previous = (&start, &first);
+        for key in keys.chain(Some((&end, &last))) {
+            if let Some((ratio, edge)) = segment(previous, key) {
+                let (from, to) = (previous.0, key.0);
+                let time = match (first_key, last_key) {
+                    (Some(first_key), _) if to <= first_key => {
+                        let distances = (duration(from, first_key), duration(to, first_key));
+                        float(first_key) - self.sampler().extrapolated_distance(true, distances, ratio)
+                    },
+                    (_, Some(last_key)) if from >= last_key => {
+                        let distances = (duration(last_key, from), duration(last_key, to));
+                        float(last_key) + self.sampler().extrapolated_distance(false, distances, ratio)
+                    },
+                    _ => float(from) + ratio * duration::<T,F>(from, to)
+                };
+                crossings.push(Crossing { time, edge });
+            }
+            previous = key;
+        }
+        Ok(crossings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::{Extrapolation, LerpSampler};
+    use crate::timelines::{LerpBtreeTimeline, StepBtreeTimeline};
+    use std::collections::BTreeMap;
+
+    const HEALTH: [(u32, f64); 4] = [(0, 100.0), (10, -20.0), (20, 40.0), (30, 0.0)];
+
+    #[test]
+    fn linear_threshold() {
+        let timeline = LerpBtreeTimeline::<u32, f64, f64>::new(HEALTH.iter().cloned().collect());
+        let crossings = timeline.try_crossings(0..=30, 0.0).unwrap();
+        assert_eq!(crossings.iter().map(|crossing| crossing.edge).collect::<Vec<_>>(), vec![Edge::Falling, Edge::Rising]);
+        assert!((crossings[0].time - 25.0 / 3.0).abs() < 1e-9);
+        assert!((crossings[1].time - 40.0 / 3.0).abs() < 1e-9);
+        // the value reaches 0 at the end of the range but does not go below
+        assert_eq!(timeline.try_crossings(12..=30, 0.0).unwrap().len(), 1);
+        assert_eq!(timeline.try_crossings(0..=40, 0.0).unwrap().last(), Some(&Crossing { time: 30.0, edge: Edge::Falling }));
+        let (start, end) = (5, 4);
+        assert_eq!(timeline.try_crossings(start..=end, 0.0), Err(SampleError::OutOfOrder));
+    }
+
+    #[test]
+    fn step_threshold_and_predicate() {
+        let timeline = StepBtreeTimeline::<u32, f64>::new(HEALTH.iter().cloned().collect());
+        assert_eq!(timeline.try_crossings(5..=35, 0.0).unwrap(), vec![
+            Crossing { time: 10.0, edge: Edge::Falling },
+            Crossing { time: 20.0, edge: Edge::Rising }
+        ]);
+        let low = timeline.try_edges(0..=30, |health: &f64| *health < 50.0).unwrap();
+        assert_eq!(low, vec![Crossing { time: 10.0, edge: Edge::Rising }]);
+    }
+
+    #[test]
+    fn extrapolation_policies() {
+        let keys: BTreeMap<i32, f64> = vec![(0, 0.0), (10, 10.0)].into_iter().collect();
+        let freeze = LerpSampler::builder().extrapolation(Extrapolation::Freeze(5.0)).build();
+        let timeline = Timeline::with_sampler(keys.clone(), freeze);
+        // the value goes on up to 15 at 15, then stays at 15
+        assert_eq!(timeline.try_crossings(0..=30, 12.0).unwrap(), vec![Crossing { time: 12.0, edge: Edge::Rising }]);
+        assert_eq!(timeline.try_crossings(12..=30, 14.0).unwrap(), vec![Crossing { time: 14.0, edge: Edge::Rising }]);
+        assert_eq!(timeline.try_crossings(0..=30, 15.0).unwrap(), vec![Crossing { time: 15.0, edge: Edge::Rising }]);
+        assert!(timeline.try_crossings(0..=30, 16.0).unwrap().is_empty());
+        assert_eq!(timeline.try_crossings(-20..=0, -3.0).unwrap(), vec![Crossing { time: -3.0, edge: Edge::Rising }]);
+        let edges: Vec<Crossing<f64>> = timeline.try_edges(0..=30, |value: &f64| *value >= 12.0).unwrap();
+        assert!((edges[0].time - 12.0).abs() < 1e-9);
+
+        let decay = Timeline::with_sampler(keys, LerpSampler::builder().future_extrapolation(Extrapolation::Decay(10.0)).build());
+        // covers 2 when d - d²/20 = 2
+        let crossing = decay.try_crossings(0..=30, 12.0).unwrap()[0];
+        assert!((crossing.time - (10.0 + 10.0 - 60f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn linear_predicate() {
+        let timeline = LerpBtreeTimeline::<u32, f64, f64>::new(HEALTH.iter().cloned().collect());
+        let edges: Vec<Crossing<f64>> = timeline.try_edges(0..=30, |health: &f64| *health < 50.0).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].edge, Edge::Rising);
+        assert!((edges[0].time - 50.0 / 12.0).abs() < 1e-9);
+    }
+}
+
+#[cfg(feature = "nalgebra_impl")]
+#[cfg(test)]
+mod tests_nalgebra {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn distance_to_target() {
+        let path = vec![(0, Vector2::new(-20.0, 3.0)), (10, Vector2::new(0.0, 3.0)), (20, Vector2::new(20.0, 3.0))];
+        let timeline = LerpBtreeTimeline::<u32, Vector2<f64>, f64>::new(path.into_iter().collect());
+        let target = Vector2::new(0.0, 0.0);
+        let edges: Vec<Crossing<f64>> = timeline.try_edges(0..=20, |position: &Vector2<f64>| (position - target).norm() < 5.0).unwrap();
+        // within 5 of the target between x = -4 and x = 4
+        assert_eq!(edges.iter().map(|edge| edge.edge).collect::<Vec<_>>(), vec![Edge::Rising, Edge::Falling]);
+        assert!((edges[0].time - 8.0).abs() < 1e-9);
+        assert!((edges[1].time - 12.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::interface::{TimeLine, DataContainer, DataContainerMut, DataSampler, SampleError};
+use super::Timeline;
+
+/// Data keys around the instant of the last query
+struct Segment<T,V> {
+    /// Most recent data key strictly before the segment
+    start: Option<T>,
+    /// Oldest data key at or after the segment
+    end: Option<T>,
+    /// Copies of the past data keys given to the sampler (most recent first)
+    past: Vec<(T,V)>,
+    /// Copies of the future data keys given to the sampler (oldest first)
+    future: Vec<(T,V)>,
+    /// Whether the copies are all the past and future data keys
+    past_copied: bool,
+    future_copied: bool
+}
+
+impl<T: Ord, V> Segment<T,V> {
+    /// Whether splitting the data keys at instant `time` gives the same past and future
+    fn contains(&self, time: &T) -> bool {
+        self.start.as_ref().map_or(true, |start| start < time)
+            && self.end.as_ref().map_or(true, |end| time <= end)
+    }
+}
+
+/// Playback cursor over a [Timeline](struct.Timeline.html), for queries at close instants (ex: animation at 60 Hz)
+///
+/// The cursor remembers the data keys bracketing the instant of the last query, and copies of the keys around them.
+/// As long as the queries stay between the same 2 data keys, sampling does not search the data keys,
+/// only leaving the segment (forward or backward) does. Sampling a long curve at a steady rate is O(1) amortized.
+///
+/// If the sampler needs more data keys than the copies (2 on each side), the missing ones are searched and copied.
+/// Setting or removing a data key through the cursor, or using `timeline_mut`, forgets the segment.
+pub struct TimelineCursor<C,S,T,V> {
+    timeline: Timeline<C,S,T,V>,
+    segment: Option<Segment<T,V>>,
+    searches: usize
+}
+
+impl<C,S,T,V> TimelineCursor<C,S,T,V>
+where
+    C: DataContainer<T,V> + DataContainerMut<T,V>,
+    S: DataSampler<T,V>,
+    T: Clone + Ord,
+    V: Clone {
+    pub fn new(timeline: Timeline<C,S,T,V>) -> Self {
+        TimelineCursor {
+            timeline,
+            segment: None,
+            searches: 0
+        }
+    }
+
+    /// Sample at instant `time`, moving the cursor if `time` is out of the current segment
+    pub fn try_sample_at(&mut self, time: T) -> Result<V, SampleError> {
+        if !self.segment.as_ref().map_or(false, |segment| segment.contains(&time)) {
+            self.seek(&time);
+        }
+        let segment = self.segment.as_ref().expect("the cursor is on a segment");
+        let container: &C = &self.timeline;
+        let (mut past_used, mut future_used) = (0, 0);
+        let result = {
+            // the copies first, then the data keys after them if the sampler needs more
+            let mut past = segment.past.iter().map(|(t, v)| (t, v))
+                .chain(Some(()).filter(|_| !segment.past_copied).into_iter()
+                    .flat_map(|_| container.split_at(&time).0.skip(segment.past.len())))
+                .inspect(|_| past_used += 1);
+            let mut future = segment.future.iter().map(|(t, v)| (t, v))
+                .chain(Some(()).filter(|_| !segment.future_copied).into_iter()
+                    .flat_map(|_| container.split_at(&time).1.skip(segment.future.len())))
+                .inspect(|_| future_used += 1);
+            self.timeline.sampler().try_sample(&mut past, &mut future, time.clone())
+        };
+        if past_used > segment.past.len() || future_used > segment.future.len() {
+            self.copy_more_keys(&time, past_used, future_used);
+        }
+        result
+    }
+
+    /// Same as `try_sample_at` but returns None on failure
+    pub fn sample_at(&mut self, time: T) -> Option<V> {
+        self.try_sample_at(time).ok()
+    }
+
+    /// Number of times the data keys were searched
+    pub fn searches(&self) -> usize {
+        self.searches
+    }
+
+    pub fn timeline(&self) -> &Timeline<C,S,T,V> {
+        &self.timeline
+    }
+
+    /// The timeline, the cursor forgets its segment since the data keys can change
+    pub fn timeline_mut(&mut self) -> &mut Timeline<C,S,T,V> {
+        self.segment = None;
+        &mut self.timeline
+    }
+
+    pub fn into_inner(self) -> Timeline<C,S,T,V> {
+        self.timeline
+    }
+
+    /// Move the cursor to the segment of instant `time`
+    fn seek(&mut self, time: &T) {
+        self.searches += 1;
+        let (past, future) = self.timeline.split_at(time);
+        let (past, past_copied) = copy_keys(past, 2);
+        let (future, future_copied) = copy_keys(future, 2);
+        self.segment = Some(Segment {
+            start: past.first().map(|(t, _)| t.clone()),
+            end: future.first().map(|(t, _)| t.clone()),
+            past,
+            future,
+            past_copied,
+            future_copied
+        });
+    }
+
+    /// Copy more data keys around instant `time`, in the current segment
+    fn copy_more_keys(&mut self, time: &T, past: usize, future: usize) {
+        self.searches += 1;
+        let (past_keys, future_keys) = self.timeline.split_at(time);
+        let segment = self.segment.as_mut().expect("the cursor is on a segment");
+        let (past, past_copied) = copy_keys(past_keys, past);
+        let (future, future_copied) = copy_keys(future_keys, future);
+        segment.past = past;
+        segment.future = future;
+        segment.past_copied = past_copied;
+        segment.future_copied = future_copied;
+    }
+}
+
+/// Copy the `count` first data keys, and tell if they are all the data keys
+fn copy_keys<'a, T, V, I>(mut keys: I, count: usize) -> (Vec<(T,V)>, bool)
+where
+    T: Clone + 'a,
+    V: Clone + 'a,
+    I: Iterator<Item = (&'a T, &'a V)> {
+    let copies = keys.by_ref().take(count).map(|(t, v)| (t.clone(), v.clone())).collect();
+    (copies, keys.next().is_none())
+}
+
+impl<C,S,T,V> TimeLine<T> for TimelineCursor<C,S,T,V>
+where
+    C: DataContainer<T,V> + DataContainerMut<T,V>,
+    S: DataSampler<T,V>,
+    T: Clone + Ord,
+    V: Clone {
+    type Item = V;
+    /// Does not move the cursor, use `try_sample_at` for that
+    fn try_get_sample(&self, time: T) -> Result<V, SampleError> {
+        self.timeline.try_get_sample(time)
+    }
+    fn set_key(&mut self, time: T, value: V) {
+        self.timeline_mut().set_key(time, value);
+    }
+    fn remove_key(&mut self, time: T) -> Option<V> {
+        self.timeline_mut().remove_key(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::{CubicSampler, LerpSampler};
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    fn cursor() -> TimelineCursor<BTreeMap<i32, f64>, LerpSampler, i32, f64> {
+        TimelineCursor::new(LerpBtreeTimeline::new((0..10).map(|i| (i * 100, f64::from(i % 2) * 100.0)).collect()))
+    }
+
+    #[test]
+    fn playback() {
+        let mut cursor = cursor();
+        let timeline = LerpBtreeTimeline::<i32, f64, f64>::new(cursor.timeline().iter().map(|(t, v)| (*t, *v)).collect());
+        for time in (-50..1000).step_by(10) {
+            assert_eq!(cursor.try_sample_at(time), timeline.try_get_sample(time), "at {}", time);
+        }
+        // one search per segment, plus the extrapolated segments on each side
+        assert_eq!(cursor.searches(), 11);
+        // seek backward
+        assert_eq!(cursor.sample_at(150), Some(50.0));
+        assert_eq!(cursor.sample_at(160), Some(40.0));
+        assert_eq!(cursor.searches(), 12);
+    }
+
+    #[test]
+    fn edits_forget_the_segment() {
+        let mut cursor = cursor();
+        assert_eq!(cursor.sample_at(150), Some(50.0));
+        cursor.set_key(150, 0.0);
+        assert_eq!(cursor.sample_at(150), Some(0.0));
+        cursor.remove_key(150);
+        assert_eq!(cursor.sample_at(150), Some(50.0));
+        cursor.timeline_mut().insert(200, 100.0);
+        assert_eq!(cursor.sample_at(150), Some(100.0));
+    }
+
+    #[test]
+    fn cubic() {
+        let keys = || (0..10).map(|i| (i * 10, f64::from(i * i))).collect::<BTreeMap<i32, f64>>();
+        let timeline: Timeline<_, CubicSampler, i32, f64> = Timeline::new(keys());
+        let mut cursor = TimelineCursor::new(Timeline::<_, CubicSampler, i32, f64>::new(keys()));
+        for time in (0..90).chain(40..50) {
+            assert_eq!(cursor.try_sample_at(time), timeline.try_get_sample(time));
+        }
+        // 10 segments, the extrapolation before the first data key needs a third key, and 2 seeks backward
+        assert_eq!(cursor.searches(), 13);
+    }
+}
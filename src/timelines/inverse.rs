@@ -0,0 +1,181 @@
+use crate::interface::{TimeLine, DataContainer, DataContainerMut, SampleError};
+use crate::samplers::LerpSampler;
+use num_traits::{Float, NumCast, ToPrimitive};
+use std::ops::Sub;
+use super::Timeline;
+
+impl<C,T,F> Timeline<C, LerpSampler<F>, T, F>
+where
+    C: DataContainer<T,F>,
+    for<'a> &'a C: IntoIterator<Item = (&'a T, &'a F)>,
+    T: Clone + Sub<Output = T> + ToPrimitive,
+    F: Float {
+    /// Instants at which the value is `value` (ex: when a progress reached 0.5), oldest first
+    ///
+    /// Only the curve between the first and the last data keys is inverted, not the extrapolation.
+    /// On a flat segment at `value`, only the start of the segment is given
+    pub fn times_at(&self, value: F) -> Vec<F> {
+        self.attained(value).collect()
+    }
+
+    /// First instant at which the value is `value`, the only one if the timeline is monotone
+    /// (ex: a progress from 0 to 1)
+    ///
+    /// Fails if there is no data key, or if the value is never attained between the first and the last data keys
+    pub fn try_time_at(&self, value: F) -> Result<F, SampleError> {
+        let container: &C = self;
+        if container.into_iter().next().is_none() {
+            return Err(SampleError::Empty);
+        }
+        self.attained(value).next().ok_or(SampleError::OutOfRange)
+    }
+
+    /// Same as `try_time_at` but returns None on failure
+    pub fn time_at(&self, value: F) -> Option<F> {
+        self.try_time_at(value).ok()
+    }
+
+    /// Instants at which each segment between 2 data keys reaches `value`, and the data keys at `value`
+    fn attained(&self, value: F) -> impl Iterator<Item = F> + '_ {
+        let container: &C = self;
+        let mut previous: Option<(&T, &F)> = None;
+        container.into_iter().filter_map(move |(time, key)| {
+            let attained = match previous {
+                Some((_, start)) if *key == value && *start == value => None,
+                _ if *key == value => Some(float(time.clone())),
+                Some((start_time, start)) if *start != value && (*start < value) != (*key < value) => {
+                    let ratio = (value - *start) / (*key - *start);
+                    Some(float::<T,F>(start_time.clone()) + ratio * float(time.clone() - start_time.clone()))
+                },
+                _ => None
+            };
+            previous = Some((time, key));
+            attained
+        })
+    }
+}
+
+/// Time converted to F, NaN if F can't represent it
+fn float<T: ToPrimitive, F: Float>(time: T) -> F {
+    <F as NumCast>::from(time).unwrap_or_else(F::nan)
+}
+
+/// Adapter exposing the inverse of a monotone timeline as a [TimeLine](../interface/trait.TimeLine.html):
+/// the value is the time and the time is the value (ex: at what time a progress reached 0.5)
+///
+/// `get_sample(value)` is the first instant the inner timeline reaches `value` (see `Timeline::try_time_at`).
+/// `set_key(value, time)` adds a data key to the inner timeline at the instant of T nearest to `time`
+/// (converted with [NumCast](https://docs.rs/num-traits/*/num_traits/cast/trait.NumCast.html)).
+/// If T can't represent this instant (ex: a negative time for u32), the data key is lost, nothing is added.
+/// `remove_key(value)` removes the data key at the instant `value` is first reached, if this instant is a data key
+pub struct Inverse<C,T,F> {
+    inner: Timeline<C, LerpSampler<F>, T, F>
+}
+
+impl<C,T,F> Inverse<C,T,F>
+where
+    C: DataContainer<T,F>,
+    for<'a> &'a C: IntoIterator<Item = (&'a T, &'a F)>,
+    T: Clone + Sub<Output = T> + NumCast,
+    F: Float {
+    pub fn new(inner: Timeline<C, LerpSampler<F>, T, F>) -> Self {
+        Inverse {
+            inner
+        }
+    }
+
+    /// The inverted timeline
+    pub fn inner(&self) -> &Timeline<C, LerpSampler<F>, T, F> {
+        &self.inner
+    }
+
+    /// The inverted timeline
+    pub fn inner_mut(&mut self) -> &mut Timeline<C, LerpSampler<F>, T, F> {
+        &mut self.inner
+    }
+
+    /// Get back the inverted timeline
+    pub fn into_inner(self) -> Timeline<C, LerpSampler<F>, T, F> {
+        self.inner
+    }
+}
+
+impl<C,T,F> TimeLine<F> for Inverse<C,T,F>
+where
+    C: DataContainer<T,F> + DataContainerMut<T,F>,
+    for<'a> &'a C: IntoIterator<Item = (&'a T, &'a F)>,
+    T: Clone + Sub<Output = T> + NumCast,
+    F: Float {
+    type Item = F;
+    fn try_get_sample(&self, value: F) -> Result<Self::Item, SampleError> {
+        self.inner.try_time_at(value)
+    }
+    fn set_key(&mut self, value: F, time: Self::Item) {
+        if let Some(time) = T::from(time.round()) {
+            self.inner.insert_key(time, value);
+        }
+    }
+    fn remove_key(&mut self, value: F) -> Option<Self::Item> {
+        let time = self.inner.time_at(value)?;
+        let key = T::from(time).filter(|key| float::<T,F>(key.clone()) == time)?;
+        self.inner.remove_key(&key)?;
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+
+    #[test]
+    fn progress() {
+        let progress = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 0.0), (10, 0.2), (30, 0.6), (40, 1.0)].into_iter().collect());
+        assert_eq!(progress.try_time_at(0.5), Ok(25.0));
+        assert_eq!(progress.try_time_at(0.2), Ok(10.0));
+        assert_eq!(progress.try_time_at(1.0), Ok(40.0));
+        assert_eq!(progress.times_at(0.1), vec![5.0]);
+        assert_eq!(progress.try_time_at(1.5), Err(SampleError::OutOfRange));
+        let empty = LerpBtreeTimeline::<u32, f64, f64>::new(Default::default());
+        assert_eq!(empty.try_time_at(0.5), Err(SampleError::Empty));
+        assert!(empty.times_at(0.5).is_empty());
+    }
+
+    #[test]
+    fn every_time() {
+        let timeline = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 0.0), (10, 1.0), (20, 0.0), (30, 0.0), (40, 2.0)].into_iter().collect());
+        assert_eq!(timeline.times_at(0.5), vec![5.0, 15.0, 32.5]);
+        assert_eq!(timeline.times_at(1.0), vec![10.0, 35.0]);
+        // the flat segment only gives its start
+        assert_eq!(timeline.times_at(0.0), vec![0.0, 20.0]);
+    }
+
+    #[test]
+    fn time_types() {
+        // i64 and u64 don't convert to f64 with Into
+        let progress = LerpBtreeTimeline::<i64, f64, f64>::new(vec![(-10, 0.0), (10, 1.0)].into_iter().collect());
+        assert_eq!(progress.try_time_at(0.25), Ok(-5.0));
+        let mut inverse = Inverse::new(LerpBtreeTimeline::<u64, f32, f32>::new(vec![(0, 0.0), (10, 0.5)].into_iter().collect()));
+        assert_eq!(inverse.get_sample(0.25), Some(5.0));
+        assert_eq!(inverse.remove_key(0.5), Some(10.0));
+    }
+
+    #[test]
+    fn adapter() {
+        let progress = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 0.0), (10, 0.5)].into_iter().collect());
+        let mut inverse = Inverse::new(progress);
+        assert_eq!(inverse.get_sample(0.25), Some(5.0));
+        inverse.set_key(1.0, 30.0);
+        assert_eq!(inverse.get_sample(0.75), Some(20.0));
+        assert_eq!(inverse.inner().get_sample(30), Some(1.0));
+        // 0.75 is reached between 2 data keys
+        assert_eq!(inverse.remove_key(0.75), None);
+        assert_eq!(inverse.remove_key(0.5), Some(10.0));
+        assert_eq!(inverse.get_sample(0.5), Some(15.0));
+        // the time is rounded to the nearest instant, or lost if out of range
+        inverse.set_key(2.0, 40.7);
+        assert_eq!(inverse.inner().get_sample(41), Some(2.0));
+        inverse.set_key(3.0, -1.0);
+        assert_eq!(inverse.into_inner().len(), 3);
+    }
+}
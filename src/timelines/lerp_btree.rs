@@ -1,74 +1,16 @@
-use crate::interface::{TimeLine, DataContainer, DataSampler};
-use crate::samplers::{LerpSampler, Lerp};
+use crate::samplers::LerpSampler;
+use crate::timelines::Timeline;
 use std::collections::BTreeMap;
-use std::cmp::Ord;
-use std::ops::{Sub};
-use num_traits::Float;
-use std::ops::{Deref, DerefMut};
 
 
 /// Linear interpolation sampler and b-tree map container
-#[allow(dead_code)]
-pub struct LerpBtreeTimeline<T,V: Lerp<F>,F: Float> {
-    inner: BTreeMap<T,V>,
-    _phantom: std::marker::PhantomData<F>
-}
-
-impl<T,V,F> LerpBtreeTimeline<T,V,F>
-where
-    F: Float,
-    T: Clone + Ord + Sub<Output = T> + Into<F>,
-    V: Clone + Lerp<F> {
-    #[allow(dead_code)]
-    pub fn new(btreemap: BTreeMap<T,V>) -> Self {
-        LerpBtreeTimeline{
-            inner: btreemap,
-            _phantom: std::marker::PhantomData::<F>
-        }
-    }
-}
-
-impl<T,V,F> Deref for LerpBtreeTimeline<T,V,F> 
-where
-    V: Lerp<F>,
-    F: Float {
-    type Target = BTreeMap<T,V>;
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-impl<V,T,F> DerefMut for LerpBtreeTimeline<T,V,F> 
-where
-    V: Lerp<F>,
-    F: Float {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
-    }
-}
-
-impl<T,V,F: Float> TimeLine<T> for LerpBtreeTimeline<T,V,F>
-where 
-    F: Float,
-    T: Clone + Ord + Sub<Output = T> + Into<F>,
-    V: Clone + Lerp<F> {
-    type Item = V;
-    fn get_sample(&self, time: T) -> Option<Self::Item> {
-        let (mut past, mut future) = self.split_at(time.clone());
-        LerpSampler::sample(&mut past, &mut future, time)
-    }
-    fn set_key(&mut self, time: T, value: Self::Item) {
-        self.insert(time, value);
-    }
-    fn remove_key(&mut self, time: T) -> Option<Self::Item> {
-        self.remove(&time)
-    }
-}
+pub type LerpBtreeTimeline<T,V,F> = Timeline<BTreeMap<T,V>, LerpSampler<F>, T, V>;
 
 #[cfg(feature = "nalgebra_impl")]
 #[cfg(test)]
 mod tests_nalgebra {
     use super::*;
+    use crate::interface::{TimeLine, SampleError};
     use nalgebra::Vector1;
     use std::iter::FromIterator;
     use nalgebra::Scalar;
@@ -104,4 +46,55 @@ mod tests_nalgebra {
         assert_eq!(bmap.get_sample(15), Some(Vector1::new(20)));
         assert_eq!(bmap.get_sample(7), Some(Vector1::new(4)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sample_errors() {
+        let mut bmap = LerpBtreeTimeline::<i32,Vector1<i32>,f64>::new(BTreeMap::new());
+        assert_eq!(bmap.try_get_sample(10), Err(SampleError::Empty));
+        bmap.set_key(10, Vector1::new(10));
+        assert_eq!(bmap.try_get_sample(15), Err(SampleError::NotEnoughKeys));
+        assert_eq!(bmap.try_smooth_correct((10, Vector1::new(5)), (10, 15)), Err(SampleError::NotEnoughKeys));
+        assert_eq!(bmap.get(&10), Some(&Vector1::new(10)));
+    }
+}
+#[cfg(feature = "serde")]
+#[cfg(test)]
+mod tests_serde {
+    use super::*;
+    use crate::interface::TimeLine;
+    use crate::samplers::{LerpSampler, Extrapolation};
+    use std::iter::FromIterator;
+
+    #[test]
+    fn json_round_trip() {
+        let sampler = LerpSampler::builder().future_extrapolation(Extrapolation::Limit(5.0)).build();
+        let timeline: LerpBtreeTimeline<u32, f64, f64> = LerpBtreeTimeline::with_sampler(BTreeMap::from_iter(vec![(10, 2.5), (0, 1.5)]), sampler);
+        let json = serde_json::to_string(&timeline).unwrap();
+        assert_eq!(json, r#"{"keys":[[0,1.5],[10,2.5]],"sampler":{"past_extrapolation":"Unbounded","future_extrapolation":{"Limit":5.0}}}"#);
+
+        let timeline: LerpBtreeTimeline<u32, f64, f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(timeline.get_sample(5), Some(2.0));
+        assert_eq!(timeline.get_sample(20), None);
+        assert_eq!(*timeline.sampler(), sampler);
+    }
+
+    #[cfg(feature = "nalgebra_impl")]
+    #[test]
+    fn nalgebra_values() {
+        use nalgebra::Vector2;
+        let keys = vec![(0, Vector2::new(0.0, 1.0)), (10, Vector2::new(10.0, 3.0))];
+        let timeline: LerpBtreeTimeline<u32, Vector2<f64>, f64> = LerpBtreeTimeline::new(BTreeMap::from_iter(keys));
+        let json = serde_json::to_string(&timeline).unwrap();
+        let timeline: LerpBtreeTimeline<u32, Vector2<f64>, f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(timeline.get_sample(5), Some(Vector2::new(5.0, 2.0)));
+    }
+
+    #[test]
+    fn unsorted_and_duplicate_keys() {
+        let json = r#"{"keys":[[10,2.5],[0,1.5]],"sampler":{"past_extrapolation":"Refuse","future_extrapolation":"Unbounded"}}"#;
+        let timeline: LerpBtreeTimeline<u32, f64, f64> = serde_json::from_str(json).unwrap();
+        assert_eq!(timeline.get_sample(5), Some(2.0));
+        let json = r#"{"keys":[[0,2.5],[0,1.5]],"sampler":{"past_extrapolation":"Refuse","future_extrapolation":"Unbounded"}}"#;
+        assert!(serde_json::from_str::<LerpBtreeTimeline<u32, f64, f64>>(json).is_err());
+    }
+}
@@ -1,6 +1,27 @@
 /*!
  * Contains [Timeline](../interface/trait.TimeLine.html) implementations
+ * and adapters
  */
+mod timeline;
 mod lerp_btree;
+mod step_btree;
+mod relative_now;
+mod retention;
+mod timeline_set;
+mod resample;
+mod cursor;
+mod simplify;
+mod aggregate;
+mod crossing;
+mod inverse;
 
-pub use lerp_btree::LerpBtreeTimeline;
\ No newline at end of file
+pub use timeline::Timeline;
+pub use lerp_btree::LerpBtreeTimeline;
+pub use step_btree::StepBtreeTimeline;
+pub use relative_now::RelativeNow;
+pub use retention::Retention;
+pub use timeline_set::TimelineSet;
+pub use resample::Resample;
+pub use cursor::TimelineCursor;
+pub use crossing::{CrossingTimeLine, Crossing, Edge};
+pub use inverse::Inverse;
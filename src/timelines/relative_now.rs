@@ -0,0 +1,222 @@
+use crate::interface::{TimeLine, DerivativeTimeLine, SampleError};
+use crate::samplers::Lerp;
+use num_traits::{Float, NumCast, ToPrimitive};
+use std::ops::{Add, RangeInclusive, Sub};
+use super::{CrossingTimeLine, Crossing};
+
+/// Adapter that makes every time given to a timeline relative to a movable "now"
+///
+/// Every method of [TimeLine](../interface/trait.TimeLine.html) takes an offset from `now`
+/// instead of an absolute time: `get_sample(0)` samples the inner timeline at `now`.
+/// Offsets in the past are negative with a signed time type (ex: i64, f64).
+/// With an unsigned time type (ex: u32), `past` and `future` convert an offset to an absolute time
+/// to use with the inner timeline: `relative.inner().get_sample(relative.past(5))`.
+pub struct RelativeNow<TL, T> {
+    inner: TL,
+    now: T
+}
+
+impl<TL, T> RelativeNow<TL, T>
+where
+    TL: TimeLine<T>,
+    T: Clone + Add<Output = T> {
+    pub fn new(inner: TL, now: T) -> Self {
+        RelativeNow {
+            inner,
+            now
+        }
+    }
+
+    /// The absolute instant offsets are relative to
+    pub fn now(&self) -> T {
+        self.now.clone()
+    }
+
+    /// Move now to the absolute instant `now`
+    pub fn set_now(&mut self, now: T) {
+        self.now = now;
+    }
+
+    /// Move now forward by `elapsed`
+    pub fn advance(&mut self, elapsed: T) {
+        self.now = self.now.clone() + elapsed;
+    }
+
+    /// Convert an offset relative to now to an absolute time
+    pub fn absolute(&self, offset: T) -> T {
+        self.now.clone() + offset
+    }
+
+    /// Absolute time `offset` after now, same as `absolute`
+    pub fn future(&self, offset: T) -> T {
+        self.absolute(offset)
+    }
+
+    /// Absolute time `offset` before now
+    pub fn past(&self, offset: T) -> T
+    where T: Sub<Output = T> {
+        self.now.clone() - offset
+    }
+
+    /// The adapted timeline, using absolute time
+    pub fn inner(&self) -> &TL {
+        &self.inner
+    }
+
+    /// The adapted timeline, using absolute time
+    pub fn inner_mut(&mut self) -> &mut TL {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> TL {
+        self.inner
+    }
+}
+
+impl<TL, T> TimeLine<T> for RelativeNow<TL, T>
+where
+    TL: TimeLine<T>,
+    T: Clone + Add<Output = T> {
+    type Item = TL::Item;
+    fn try_get_sample(&self, time: T) -> Result<Self::Item, SampleError> {
+        self.inner.try_get_sample(self.absolute(time))
+    }
+    fn set_key(&mut self, time: T, value: Self::Item) {
+        let time = self.absolute(time);
+        self.inner.set_key(time, value);
+    }
+    fn remove_key(&mut self, time: T) -> Option<Self::Item> {
+        let time = self.absolute(time);
+        self.inner.remove_key(time)
+    }
+    fn try_smooth_correct(&mut self, correction: (T, Self::Item), correction_time_segment: (T,T)) -> Result<Self::Item, SampleError> {
+        let correction = (self.absolute(correction.0), correction.1);
+        let correction_time_segment = (self.absolute(correction_time_segment.0), self.absolute(correction_time_segment.1));
+        self.inner.try_smooth_correct(correction, correction_time_segment)
+    }
+}
+
+impl<TL, T> DerivativeTimeLine<T> for RelativeNow<TL, T>
+where
+    TL: DerivativeTimeLine<T>,
+    T: Clone + Add<Output = T> {
+    type Derivative = TL::Derivative;
+    fn try_get_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        self.inner.try_get_derivative(self.absolute(time))
+    }
+    fn try_get_second_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        self.inner.try_get_second_derivative(self.absolute(time))
+    }
+}
+
+impl<TL, T> CrossingTimeLine<T> for RelativeNow<TL, T>
+where
+    TL: CrossingTimeLine<T>,
+    T: Clone + Add<Output = T> + ToPrimitive {
+    /// The instants are offsets from now
+    fn try_crossings(&self, range: RangeInclusive<T>, threshold: Self::Item) -> Result<Vec<Crossing<Self::Item>>, SampleError>
+    where Self::Item: Float {
+        let (start, end) = range.into_inner();
+        let crossings = self.inner.try_crossings(self.absolute(start)..=self.absolute(end), threshold)?;
+        Ok(self.relative_crossings(crossings))
+    }
+    /// The instants are offsets from now
+    fn try_edges<F, P>(&self, range: RangeInclusive<T>, predicate: P) -> Result<Vec<Crossing<F>>, SampleError>
+    where
+        Self::Item: Clone + Lerp<F>,
+        F: Float,
+        P: FnMut(&Self::Item) -> bool {
+        let (start, end) = range.into_inner();
+        let edges = self.inner.try_edges(self.absolute(start)..=self.absolute(end), predicate)?;
+        Ok(self.relative_crossings(edges))
+    }
+}
+
+impl<TL, T> RelativeNow<TL, T>
+where
+    TL: TimeLine<T>,
+    T: Clone + Add<Output = T> + ToPrimitive {
+    /// Convert the absolute instants of crossings to offsets from now
+    fn relative_crossings<F: Float>(&self, crossings: Vec<Crossing<F>>) -> Vec<Crossing<F>> {
+        let now = <F as NumCast>::from(self.now.clone()).unwrap_or_else(F::nan);
+        crossings.into_iter()
+            .map(|crossing| Crossing { time: crossing.time - now, edge: crossing.edge })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+    use std::iter::FromIterator;
+
+    fn timeline(now: i32) -> RelativeNow<LerpBtreeTimeline<i32, f64, f64>, i32> {
+        let data = vec![(10, 10.0), (15, 20.0)];
+        RelativeNow::new(LerpBtreeTimeline::new(BTreeMap::from_iter(data)), now)
+    }
+
+    #[test]
+    fn sample_relative() {
+        let mut timeline = timeline(15);
+        assert_eq!(timeline.get_sample(0), Some(20.0));
+        assert_eq!(timeline.get_sample(-5), Some(10.0));
+        assert_eq!(timeline.get_sample(2), Some(24.0));
+        timeline.advance(2);
+        assert_eq!(timeline.now(), 17);
+        assert_eq!(timeline.get_sample(0), Some(24.0));
+        timeline.set_now(13);
+        assert_eq!(timeline.get_sample(0), Some(16.0));
+    }
+
+    #[test]
+    fn edit_relative() {
+        let mut timeline = timeline(15);
+        timeline.set_key(5, 0.0);
+        assert_eq!(timeline.inner().get_sample(20), Some(0.0));
+        assert_eq!(timeline.remove_key(-5), Some(10.0));
+        assert_eq!(timeline.inner().len(), 2);
+    }
+
+    #[test]
+    fn smooth_correct_relative() {
+        let mut timeline = timeline(15);
+        assert_eq!(timeline.smooth_correct((0, 30.0), (0, 5)), Some(50.0));
+        assert_eq!(timeline.inner().get_sample(15), Some(20.0));
+        assert_eq!(timeline.inner().get_sample(20), Some(50.0));
+    }
+
+    #[test]
+    fn unsigned_time() {
+        let data = vec![(10_u32, 10.0), (15, 20.0)];
+        let mut timeline = RelativeNow::new(LerpBtreeTimeline::<u32, f64, f64>::new(BTreeMap::from_iter(data)), 15);
+        assert_eq!(timeline.past(5), 10);
+        assert_eq!(timeline.future(2), 17);
+        assert_eq!(timeline.inner().get_sample(timeline.past(3)), Some(14.0));
+        assert_eq!(timeline.get_sample(2), Some(24.0));
+        timeline.advance(5);
+        assert_eq!(timeline.inner().get_sample(timeline.past(10)), Some(10.0));
+        let past = timeline.past(7);
+        timeline.inner_mut().set_key(past, 0.0);
+        assert_eq!(timeline.remove_key(0), None);
+        assert_eq!(timeline.inner_mut().remove_key(13), Some(0.0));
+    }
+
+    #[test]
+    fn crossings_relative() {
+        use crate::timelines::Edge;
+        let timeline = timeline(15);
+        // 15.0 is crossed at 12.5, 2.5 before now
+        assert_eq!(timeline.try_crossings(-5..=0, 15.0), Ok(vec![Crossing { time: -2.5, edge: Edge::Rising }]));
+        let edges: Vec<Crossing<f64>> = timeline.edges(-5..=0, |value: &f64| *value >= 15.0).unwrap();
+        assert!((edges[0].time + 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn derivative_relative() {
+        let timeline = timeline(15);
+        assert_eq!(timeline.get_derivative(-2), Some(2.0));
+        assert_eq!(timeline.get_second_derivative(3), Some(0.0));
+    }
+}
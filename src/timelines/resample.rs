@@ -0,0 +1,171 @@
+use crate::interface::{DataContainer, DataSampler, SampleError, Instants};
+use num_traits::{CheckedAdd, Zero};
+use std::collections::VecDeque;
+use std::iter::Peekable;
+
+/// Number of passed data keys remembered, more than the built-in samplers look back at
+const PASSED: usize = 4;
+
+/// Iterator of the samples of a [Timeline](struct.Timeline.html) at regular instants,
+/// built by [resample_keys](struct.Timeline.html#method.resample_keys)
+///
+/// The data keys are split once at the first instant, then the split only moves forward:
+/// the last future data keys passed are remembered (most recent first) so the sampler can still look back at them,
+/// sampling N instants costs O(N + keys) instead of O(N log keys).
+/// If the sampler looks further back than the keys remembered, the data keys are searched again.
+///
+/// Yields `(time, sample)`, the samples that fail are yielded with the reason of the failure
+pub struct Resample<'a,C,S,T,V>
+where
+    C: DataContainer<T,V> + 'a,
+    T: 'a,
+    V: 'a {
+    container: &'a C,
+    sampler: &'a S,
+    past: C::Past<'a>,
+    passed: VecDeque<(&'a T, &'a V)>,
+    /// Whether passed data keys were forgotten, so `past` is not right after the remembered ones
+    forgotten: bool,
+    future: Peekable<C::Future<'a>>,
+    instants: Instants<T>
+}
+
+impl<'a,C,S,T,V> Resample<'a,C,S,T,V>
+where
+    C: DataContainer<T,V>,
+    T: Clone + Ord + CheckedAdd + Zero {
+    pub(crate) fn new(container: &'a C, sampler: &'a S, start: T, end: T, step: T) -> Self {
+        let (past, future) = container.split_at(&start);
+        Resample {
+            container,
+            sampler,
+            past,
+            passed: VecDeque::with_capacity(PASSED),
+            forgotten: false,
+            future: future.peekable(),
+            instants: Instants::new(start..=end, step)
+        }
+    }
+}
+
+impl<'a,C,S,T,V> Iterator for Resample<'a,C,S,T,V>
+where
+    C: DataContainer<T,V>,
+    C::Past<'a>: Clone,
+    C::Future<'a>: Clone,
+    S: DataSampler<T,V>,
+    T: Clone + Ord + CheckedAdd + Zero {
+    type Item = (T, Result<V, SampleError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.instants.next()?;
+        while let Some(key) = self.future.next_if(|(t, _)| **t < time) {
+            if self.passed.len() == PASSED {
+                self.passed.pop_front();
+                self.forgotten = true;
+            }
+            self.passed.push_back(key);
+        }
+        let container = self.container;
+        let forgotten = self.forgotten;
+        let oldest = self.passed.front().map(|(t, _)| *t);
+        // the remembered keys first, then the keys before them, searched only if the sampler needs them
+        let mut past = self.passed.iter().rev().cloned()
+            .chain(Some(self.past.clone()).filter(|_| !forgotten).into_iter().flatten())
+            .chain(oldest.filter(|_| forgotten).into_iter().flat_map(|oldest| container.split_at(oldest).0));
+        let mut future = self.future.clone();
+        let sample = self.sampler.try_sample(&mut past, &mut future, time.clone());
+        Some((time, sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::containers::{RingBuffer, SortedVec};
+    use crate::interface::{TimeLine, DataSampler, SampleError};
+    use crate::samplers::{CubicSampler, LerpSampler};
+    use crate::timelines::{Timeline, LerpBtreeTimeline, StepBtreeTimeline, RelativeNow};
+    use std::collections::BTreeMap;
+
+    const KEYS: [(i32, f64); 4] = [(0, 0.0), (10, 10.0), (15, 0.0), (30, 30.0)];
+
+    #[test]
+    fn same_as_get_sample() {
+        let btree = LerpBtreeTimeline::<i32, f64, f64>::new(KEYS.iter().cloned().collect());
+        let sorted_vec: Timeline<SortedVec<i32, f64>, LerpSampler, i32, f64> = Timeline::new(KEYS.iter().cloned().collect());
+        let mut ring_buffer: Timeline<RingBuffer<i32, f64>, LerpSampler, i32, f64> = Timeline::new(RingBuffer::with_capacity(8));
+        for (time, value) in KEYS.iter() {
+            ring_buffer.set_key(*time, *value);
+        }
+        let expected: Vec<_> = (-5..=40).step_by(3).map(|time| (time, btree.try_get_sample(time))).collect();
+        assert_eq!(btree.resample_keys(-5..=40, 3).collect::<Vec<_>>(), expected);
+        assert_eq!(sorted_vec.resample_keys(-5..=40, 3).collect::<Vec<_>>(), expected);
+        assert_eq!(ring_buffer.resample_keys(-5..=40, 3).collect::<Vec<_>>(), expected);
+        assert_eq!(btree.resample_keys(0..=30, 5).map(|(_, sample)| sample.unwrap()).collect::<Vec<_>>(),
+                   vec![0.0, 5.0, 10.0, 0.0, 10.0, 20.0, 30.0]);
+    }
+
+    #[test]
+    fn step_and_empty_ranges() {
+        let timeline = StepBtreeTimeline::<i32, f64>::new(KEYS.iter().cloned().collect());
+        let samples: Vec<_> = timeline.resample_keys(9..=16, 1).filter_map(|(_, sample)| sample.ok()).collect();
+        assert_eq!(samples, vec![0.0, 10.0, 10.0, 10.0, 10.0, 10.0, 0.0, 0.0]);
+        let (start, end) = (10, 0);
+        assert_eq!(timeline.resample_keys(start..=end, 1).count(), 0);
+        assert_eq!(timeline.resample_keys(10..=10, 1).count(), 1);
+        assert_eq!(timeline.resample_keys(0..=10, 0).count(), 0);
+        assert_eq!(timeline.resample_keys(0..=10, -1).count(), 0);
+    }
+
+    #[test]
+    fn long_range() {
+        let keys = (0..100).map(|t| (t * 10, f64::from(t % 7)));
+        let timeline = LerpBtreeTimeline::<i32, f64, f64>::new(keys.collect());
+        let cubic: Timeline<_, CubicSampler, i32, f64> = Timeline::new(timeline.iter().map(|(t, v)| (*t, *v)).collect::<BTreeMap<_,_>>());
+        // beyond the last key, the samplers look back at keys passed long ago
+        for (time, sample) in timeline.resample_keys(-20..=1020, 7) {
+            assert_eq!(sample, timeline.try_get_sample(time));
+        }
+        for (time, sample) in cubic.resample_keys(-20..=1020, 7) {
+            assert_eq!(sample, cubic.try_get_sample(time));
+        }
+    }
+
+    /// Sum of the past values, to check every past data key is given
+    struct PastSum;
+
+    impl DataSampler<i32, f64> for PastSum {
+        fn try_sample<'a>(&self, past_values: &mut dyn Iterator<Item = (&'a i32, &'a f64)>, _: &mut dyn Iterator<Item = (&'a i32, &'a f64)>, _: i32) -> Result<f64, SampleError>
+        where i32: 'a, f64: 'a {
+            Ok(past_values.map(|(_, value)| value).sum())
+        }
+    }
+
+    #[test]
+    fn forgotten_keys() {
+        let keys: BTreeMap<i32, f64> = (0..20).map(|t| (t * 10, f64::from(t))).collect();
+        let timeline: Timeline<_, PastSum, i32, f64> = Timeline::with_sampler(keys, PastSum);
+        for (time, sample) in timeline.resample_keys(-5..=250, 5) {
+            assert_eq!(sample, timeline.try_get_sample(time));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let timeline = StepBtreeTimeline::<u8, f64>::new(vec![(0, 1.0), (250, 2.0)].into_iter().collect());
+        let times: Vec<u8> = timeline.resample_keys(240..=255, 10).map(|(time, _)| time).collect();
+        assert_eq!(times, vec![240, 250]);
+        assert_eq!(timeline.resample_keys(0..=255, 100).count(), 3);
+    }
+
+    #[test]
+    fn adapters() {
+        let timeline = LerpBtreeTimeline::<i32, f64, f64>::new(KEYS.iter().cloned().collect());
+        // the trait method is not shadowed by resample_keys
+        assert_eq!(timeline.resample(0..=30, 5).collect::<Vec<_>>(), timeline.resample_keys(0..=30, 5).collect::<Vec<_>>());
+        let expected: Vec<_> = timeline.resample_keys(0..=30, 5).map(|(time, sample)| (time - 10, sample)).collect();
+        let relative = RelativeNow::new(timeline, 10);
+        assert_eq!(relative.resample(-10..=20, 5).collect::<Vec<_>>(), expected);
+        assert_eq!(relative.resample(0..=20, 0).count(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::interface::{TimeLine, DerivativeTimeLine, DataContainer, DataContainerMut, SampleError};
+use crate::samplers::Lerp;
+use num_traits::{CheckedSub, Float};
+use std::ops::{Add, DerefMut, RangeInclusive};
+use super::{CrossingTimeLine, Crossing};
+
+/// Adapter that forgets the data keys older than a time window
+///
+/// The window ends at the most recent data key set through the adapter,
+/// or at an external "now" once `set_now` or `advance` has been called.
+/// Every data key strictly before `end - window` is dropped, except the most recent of them,
+/// so the timeline can still be interpolated right at the edge of the window.
+/// At least `min_keys` data keys (2 by default, for a linear extrapolation) are kept,
+/// so the timeline can still be extrapolated once now or the edge moves past the most recent data key.
+///
+/// The adapted timeline must give access to its data container (ex: [Timeline](struct.Timeline.html)).
+/// Nothing is dropped while `end - window` is before the first instant the time type can represent
+/// (ex: the first `window` of an unsigned time).
+pub struct Retention<TL, T> {
+    inner: TL,
+    window: T,
+    newest: Option<T>,
+    now: Option<T>,
+    min_keys: usize
+}
+
+impl<TL, T> Retention<TL, T>
+where
+    TL: TimeLine<T> + DerefMut,
+    TL::Target: DataContainer<T, TL::Item> + DataContainerMut<T, TL::Item>,
+    T: Clone + PartialOrd + CheckedSub {
+    /// Keep the data keys of the last `window` of `inner`
+    ///
+    /// Nothing is dropped until a data key is set or now is moved
+    pub fn new(inner: TL, window: T) -> Self {
+        Retention {
+            inner,
+            window,
+            newest: None,
+            now: None,
+            min_keys: 2
+        }
+    }
+
+    /// Keep at least `min_keys` data keys, for samplers that need more keys to extrapolate (ex: 3 for a quadratic curve)
+    pub fn with_min_keys(mut self, min_keys: usize) -> Self {
+        self.min_keys = min_keys;
+        self.forget();
+        self
+    }
+
+    pub fn window(&self) -> T {
+        self.window.clone()
+    }
+
+    /// Change the duration kept, dropping the data keys that fall out of the new window
+    pub fn set_window(&mut self, window: T) {
+        self.window = window;
+        self.forget();
+    }
+
+    /// Move the end of the window to the instant `now`, dropping the data keys that fall out of the window
+    pub fn set_now(&mut self, now: T) {
+        self.now = Some(now);
+        self.forget();
+    }
+
+    /// Move the end of the window forward by `elapsed`
+    ///
+    /// If now was never set, it starts from the most recent data key
+    pub fn advance(&mut self, elapsed: T)
+    where T: Add<Output = T> {
+        if let Some(now) = self.now.take().or_else(|| self.newest.clone()) {
+            self.set_now(now + elapsed);
+        }
+    }
+
+    /// Oldest instant that can be sampled from the kept data keys,
+    /// if the window has an end and `end - window` can be represented by the time type
+    pub fn edge(&self) -> Option<T> {
+        self.now.as_ref().or(self.newest.as_ref())
+            .and_then(|end| end.checked_sub(&self.window))
+    }
+
+    /// The adapted timeline
+    pub fn inner(&self) -> &TL {
+        &self.inner
+    }
+
+    /// The adapted timeline, data keys set this way do not move the end of the window
+    pub fn inner_mut(&mut self) -> &mut TL {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> TL {
+        self.inner
+    }
+
+    fn forget(&mut self) {
+        if let Some(edge) = self.edge() {
+            // The most recent key before the edge is kept to interpolate up to the edge,
+            // and more of them if there is not enough keys after the edge to extrapolate
+            let after = self.inner.split_at(&edge).1.count();
+            let before = self.min_keys.saturating_sub(after).max(1);
+            let kept = self.inner.split_at(&edge).0.nth(before - 1).map(|(time, _)| time.clone());
+            if let Some(kept) = kept {
+                self.inner.remove_before(&kept);
+            }
+        }
+    }
+}
+
+impl<TL, T> TimeLine<T> for Retention<TL, T>
+where
+    TL: TimeLine<T> + DerefMut,
+    TL::Target: DataContainer<T, TL::Item> + DataContainerMut<T, TL::Item>,
+    T: Clone + PartialOrd + CheckedSub {
+    type Item = TL::Item;
+    fn try_get_sample(&self, time: T) -> Result<Self::Item, SampleError> {
+        self.inner.try_get_sample(time)
+    }
+    fn set_key(&mut self, time: T, value: Self::Item) {
+        match &self.newest {
+            Some(newest) if *newest >= time => {},
+            _ => self.newest = Some(time.clone())
+        }
+        self.inner.set_key(time, value);
+        self.forget();
+    }
+    fn remove_key(&mut self, time: T) -> Option<Self::Item> {
+        let removed = self.inner.remove_key(time.clone())?;
+        if self.newest.as_ref() == Some(&time) {
+            // The window now ends at the most recent data key left before the removed one
+            self.newest = self.inner.split_at(&time).0.next().map(|(time, _)| time.clone());
+        }
+        Some(removed)
+    }
+}
+
+impl<TL, T> DerivativeTimeLine<T> for Retention<TL, T>
+where
+    TL: DerivativeTimeLine<T> + DerefMut,
+    TL::Target: DataContainer<T, TL::Item> + DataContainerMut<T, TL::Item>,
+    T: Clone + PartialOrd + CheckedSub {
+    type Derivative = TL::Derivative;
+    fn try_get_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        self.inner.try_get_derivative(time)
+    }
+    fn try_get_second_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        self.inner.try_get_second_derivative(time)
+    }
+}
+
+impl<TL, T> CrossingTimeLine<T> for Retention<TL, T>
+where
+    TL: CrossingTimeLine<T> + DerefMut,
+    TL::Target: DataContainer<T, TL::Item> + DataContainerMut<T, TL::Item>,
+    T: Clone + PartialOrd + CheckedSub {
+    fn try_crossings(&self, range: RangeInclusive<T>, threshold: Self::Item) -> Result<Vec<Crossing<Self::Item>>, SampleError>
+    where Self::Item: Float {
+        self.inner.try_crossings(range, threshold)
+    }
+    fn try_edges<F, P>(&self, range: RangeInclusive<T>, predicate: P) -> Result<Vec<Crossing<F>>, SampleError>
+    where
+        Self::Item: Clone + Lerp<F>,
+        F: Float,
+        P: FnMut(&Self::Item) -> bool {
+        self.inner.try_edges(range, predicate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+    use std::collections::BTreeMap;
+
+    fn times(timeline: &Retention<LerpBtreeTimeline<i32, f64, f64>, i32>) -> Vec<i32> {
+        timeline.inner().keys().cloned().collect()
+    }
+
+    #[test]
+    fn window_from_newest_key() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::new(BTreeMap::new()), 10);
+        for t in 0..8 {
+            timeline.set_key(t * 3, f64::from(t * 3));
+        }
+        // newest key at 21, edge at 11, 9 is kept to interpolate at the edge
+        assert_eq!(times(&timeline), vec![9, 12, 15, 18, 21]);
+        assert_eq!(timeline.edge(), Some(11));
+        assert_eq!(timeline.get_sample(11), Some(11.0));
+        // an older key does not move the window
+        timeline.set_key(1, 1.0);
+        assert_eq!(times(&timeline), vec![9, 12, 15, 18, 21]);
+    }
+
+    #[test]
+    fn window_from_now() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::new(BTreeMap::new()), 10);
+        for t in 0..5 {
+            timeline.set_key(t * 5, f64::from(t));
+        }
+        assert_eq!(times(&timeline), vec![5, 10, 15, 20]);
+        timeline.set_now(30);
+        assert_eq!(times(&timeline), vec![15, 20]);
+        timeline.advance(5);
+        assert_eq!(timeline.edge(), Some(25));
+        assert_eq!(times(&timeline), vec![15, 20]);
+        // keys at exactly the edge are kept with the one before
+        timeline.set_key(25, 5.0);
+        timeline.set_now(35);
+        assert_eq!(times(&timeline), vec![20, 25]);
+        timeline.set_window(5);
+        assert_eq!(times(&timeline), vec![20, 25]);
+    }
+
+    #[test]
+    fn advance_past_newest_key() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::new(BTreeMap::new()), 10);
+        for t in 0..5 {
+            timeline.set_key(t * 5, f64::from(t));
+        }
+        timeline.advance(30);
+        assert_eq!(timeline.edge(), Some(40));
+        // the last 2 keys are kept to extrapolate
+        assert_eq!(times(&timeline), vec![15, 20]);
+        assert_eq!(timeline.get_sample(50), Some(10.0));
+        // a sampler needing 3 keys
+        let mut timeline = timeline.with_min_keys(3);
+        timeline.set_key(25, 5.0);
+        timeline.set_now(60);
+        assert_eq!(times(&timeline), vec![15, 20, 25]);
+    }
+
+    #[test]
+    fn crossings() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::<i32, f64, f64>::new(BTreeMap::new()), 10);
+        for t in 0..5 {
+            timeline.set_key(t * 5, f64::from(t));
+        }
+        assert_eq!(timeline.try_crossings(5..=20, 2.5), Ok(vec![Crossing { time: 12.5, edge: crate::timelines::Edge::Rising }]));
+    }
+
+    #[test]
+    fn unsigned_time() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::<u32, f64, f64>::new(BTreeMap::new()), 10);
+        // the window reaches before 0 until 10
+        timeline.set_key(0, 0.0);
+        timeline.set_key(5, 1.0);
+        assert_eq!(timeline.edge(), None);
+        assert_eq!(timeline.inner().len(), 2);
+        timeline.set_key(12, 2.0);
+        assert_eq!(timeline.edge(), Some(2));
+        assert_eq!(timeline.inner().keys().cloned().collect::<Vec<_>>(), vec![0, 5, 12]);
+        timeline.set_key(16, 3.0);
+        assert_eq!(timeline.inner().keys().cloned().collect::<Vec<_>>(), vec![5, 12, 16]);
+    }
+
+    #[test]
+    fn remove_newest_key() {
+        let mut timeline = Retention::new(LerpBtreeTimeline::new(BTreeMap::new()), 10);
+        for t in 0..5 {
+            timeline.set_key(t * 5, f64::from(t));
+        }
+        assert_eq!(timeline.remove_key(20), Some(4.0));
+        assert_eq!(timeline.edge(), Some(5));
+        assert_eq!(timeline.remove_key(20), None);
+        timeline.remove_key(15);
+        timeline.remove_key(10);
+        assert_eq!(timeline.edge(), Some(-5));
+        // the window ends at the key set, not at the removed one
+        timeline.set_key(12, 5.0);
+        assert_eq!(timeline.edge(), Some(2));
+        assert_eq!(times(&timeline), vec![5, 12]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::interface::DataContainerMut;
+use crate::samplers::{Lerp, LerpSampler};
+use num_traits::Float;
+use std::ops::Sub;
+use super::Timeline;
+
+impl<C,T,V,F> Timeline<C, LerpSampler<F>, T, V>
+where
+    C: DataContainerMut<T,V>,
+    for<'a> &'a C: IntoIterator<Item = (&'a T, &'a V)>,
+    T: Clone + Sub<Output = T> + Into<F>,
+    V: Clone + Lerp<F>,
+    F: Float {
+    /// Timeline with fewer data keys, interpolated within `tolerance` of the data keys of this one
+    /// (ex: a stream recorded at 120 Hz), and the largest error of the removed data keys
+    ///
+    /// The data keys are removed with the Ramer–Douglas–Peucker algorithme: a segment keeps the data key
+    /// furthest from the line between its ends if it is further than `tolerance`, and is split in two at this key.
+    /// `distance` measures the error between the interpolated and the recorded value
+    /// (ex: `|a, b| (a - b).abs()` for a scalar, `|a, b| (a - b).norm()` for a nalgebra vector).
+    ///
+    /// The first and last data keys are kept, but the extrapolation uses the simplified segments at each end
+    pub fn simplify<D>(&self, tolerance: F, distance: D) -> (Self, F)
+    where
+        C: Default,
+        D: FnMut(&V, &V) -> F {
+        self.simplify_into(C::default(), tolerance, distance)
+    }
+
+    /// Same as `simplify`, but the data keys kept are added to `container`
+    /// (ex: a [RingBuffer](../containers/struct.RingBuffer.html), which has no default capacity)
+    pub fn simplify_into<D>(&self, mut container: C, tolerance: F, mut distance: D) -> (Self, F)
+    where D: FnMut(&V, &V) -> F {
+        let keys: Vec<(&T, &V)> = (self as &C).into_iter().collect();
+        let mut kept = vec![false; keys.len()];
+        let mut segments = Vec::new();
+        if let Some(last) = keys.len().checked_sub(1) {
+            kept[0] = true;
+            kept[last] = true;
+            segments.push((0, last));
+        }
+        let mut max_error = F::zero();
+        while let Some((first, last)) = segments.pop() {
+            let ((t0, v0), (t1, v1)) = (keys[first], keys[last]);
+            let duration: F = (t1.clone() - t0.clone()).into();
+            let furthest = (first + 1..last)
+                .map(|index| {
+                    let (time, value) = keys[index];
+                    let ratio = (time.clone() - t0.clone()).into() / duration;
+                    (index, distance(&v0.clone().lerp(v1.clone(), ratio), value))
+                })
+                .fold(None, |furthest: Option<(usize, F)>, (index, error)| match furthest {
+                    Some((_, max)) if max >= error => furthest,
+                    _ => Some((index, error))
+                });
+            match furthest {
+                Some((index, error)) if error > tolerance => {
+                    kept[index] = true;
+                    segments.push((first, index));
+                    segments.push((index, last));
+                },
+                Some((_, error)) => max_error = max_error.max(error),
+                None => {}
+            }
+        }
+
+        for ((time, value), _) in keys.into_iter().zip(kept).filter(|(_, kept)| *kept) {
+            container.insert_key(time.clone(), value.clone());
+        }
+        (Timeline::with_sampler(container, *self.sampler()), max_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::containers::{RingBuffer, SortedVec};
+    use crate::interface::TimeLine;
+    use crate::samplers::LerpSampler;
+    use crate::timelines::{Timeline, LerpBtreeTimeline};
+
+    fn absolute(a: &f64, b: &f64) -> f64 {
+        (a - b).abs()
+    }
+
+    #[test]
+    fn straight_lines() {
+        // 0 -> 100 then 100 -> 50, recorded every 10
+        let keys = (0..=150).step_by(10).map(|t| (t, if t <= 100 { f64::from(t) } else { f64::from(200 - t) }));
+        let timeline = LerpBtreeTimeline::<i32, f64, f64>::new(keys.collect());
+        let (simplified, error) = timeline.simplify(0.0, absolute);
+        assert_eq!(simplified.iter().map(|(t, v)| (*t, *v)).collect::<Vec<_>>(), vec![(0, 0.0), (100, 100.0), (150, 50.0)]);
+        assert_eq!(error, 0.0);
+        for t in 0..=150 {
+            assert!((simplified.get_sample(t).unwrap() - timeline.get_sample(t).unwrap()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn tolerance() {
+        let keys: SortedVec<u32, f64> = vec![(0, 0.0), (1, 0.5), (2, 0.0), (3, 3.0), (4, 0.0), (5, 0.2), (6, 0.0)].into_iter().collect();
+        let timeline: Timeline<SortedVec<u32, f64>, LerpSampler, u32, f64> = Timeline::new(keys);
+        let (simplified, error) = timeline.simplify(0.5, absolute);
+        assert_eq!(simplified.as_slice(), &[(0, 0.0), (2, 0.0), (3, 3.0), (4, 0.0), (6, 0.0)]);
+        assert_eq!(error, 0.5);
+        let (simplified, error) = timeline.simplify(10.0, absolute);
+        assert_eq!(simplified.len(), 2);
+        assert_eq!(error, 3.0);
+
+        let single = LerpBtreeTimeline::<u32, f64, f64>::new(vec![(0, 1.0)].into_iter().collect());
+        assert_eq!(single.simplify(0.0, absolute).0.len(), 1);
+        let empty = LerpBtreeTimeline::<u32, f64, f64>::new(Default::default());
+        assert!(empty.simplify(0.0, absolute).0.is_empty());
+    }
+
+    #[test]
+    fn into_ring_buffer() {
+        let mut timeline: Timeline<RingBuffer<u32, f64>, LerpSampler, u32, f64> = Timeline::new(RingBuffer::with_capacity(8));
+        for (time, value) in [(0, 0.0), (1, 1.0), (2, 2.0), (3, 1.0), (4, 0.0)] {
+            timeline.set_key(time, value);
+        }
+        let (simplified, error) = timeline.simplify_into(RingBuffer::with_capacity(4), 0.0, absolute);
+        assert_eq!(simplified.iter().map(|(t, v)| (*t, *v)).collect::<Vec<_>>(), vec![(0, 0.0), (2, 2.0), (4, 0.0)]);
+        assert_eq!(error, 0.0);
+    }
+}
+
+#[cfg(feature = "nalgebra_impl")]
+#[cfg(test)]
+mod tests_nalgebra {
+    use crate::timelines::LerpBtreeTimeline;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn norm() {
+        // circle arc recorded at 120 Hz for a second
+        let keys = (0..=120).map(|i| {
+            let angle = f64::from(i) / 120.0 * std::f64::consts::FRAC_PI_2;
+            (i, Vector2::new(angle.cos(), angle.sin()))
+        });
+        let timeline = LerpBtreeTimeline::<i32, Vector2<f64>, f64>::new(keys.collect());
+        let (simplified, error) = timeline.simplify(0.01, |a, b| (a - b).norm());
+        assert!(simplified.len() < 15, "{} keys left", simplified.len());
+        assert!(error > 0.0 && error <= 0.01);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::samplers::StepSampler;
+use crate::timelines::Timeline;
+use std::collections::BTreeMap;
+
+
+/// Last value sampler and b-tree map container
+pub type StepBtreeTimeline<T,V> = Timeline<BTreeMap<T,V>, StepSampler<T>, T, V>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::TimeLine;
+    use std::iter::FromIterator;
+
+    #[test]
+    fn last_value() {
+        let mut timeline = StepBtreeTimeline::new(BTreeMap::from_iter(vec![(10, 1), (15, 2)]));
+        assert_eq!(timeline.get_sample(5), None);
+        assert_eq!(timeline.get_sample(10), Some(1));
+        assert_eq!(timeline.get_sample(13), Some(1));
+        assert_eq!(timeline.get_sample(15), Some(2));
+        assert_eq!(timeline.get_sample(42), Some(2));
+        timeline.set_key(12, 3);
+        assert_eq!(timeline.get_sample(13), Some(3));
+        assert_eq!(timeline.remove_key(12), Some(3));
+        assert_eq!(timeline.get_sample(13), Some(1));
+    }
+
+    #[test]
+    fn configured_sampler() {
+        let sampler = StepSampler::builder().max_extrapolation(5).build();
+        let timeline = StepBtreeTimeline::with_sampler(BTreeMap::from_iter(vec![(10, 1), (15, 2)]), sampler);
+        assert_eq!(timeline.get_sample(20), Some(2));
+        assert_eq!(timeline.get_sample(21), None);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::interface::{TimeLine, DerivativeTimeLine, DataContainer, DataContainerMut, DataSampler, DerivativeSampler, SampleError};
+use num_traits::{CheckedAdd, Zero};
+use std::ops::{Deref, DerefMut, RangeInclusive};
+use super::Resample;
+#[cfg(feature = "serde")]
+use crate::interface::SerdeKeys;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+
+/// Timeline made of any data container and any sampler
+///
+/// C: the datastructure holding the data keys (ex: BTreeMap<T,V>)
+/// S: the strategy used to inter/extrapolate values (ex: LerpSampler), the timeline owns a configured instance
+/// T: the type that encode time (ex: u32)
+/// V: the data type that we try to represent (ex: player position)
+///
+/// With the `serde` feature, a timeline is serialized as a struct of its `keys` and its `sampler`,
+/// see [SerdeKeys](../interface/trait.SerdeKeys.html)
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "C: SerdeKeys<T,V>, S: Serialize, T: Serialize, V: Serialize",
+    deserialize = "C: SerdeKeys<T,V>, S: Deserialize<'de>, T: Deserialize<'de>, V: Deserialize<'de>")))]
+pub struct Timeline<C,S,T,V> {
+    #[cfg_attr(feature = "serde", serde(rename = "keys", serialize_with = "SerdeKeys::serialize_keys", deserialize_with = "SerdeKeys::deserialize_keys"))]
+    inner: C,
+    sampler: S,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _phantom: std::marker::PhantomData<(T,V)>
+}
+
+impl<C,S: Default,T,V> Timeline<C,S,T,V> {
+    /// Timeline using the default configuration of the sampler
+    pub fn new(container: C) -> Self {
+        Timeline::with_sampler(container, S::default())
+    }
+}
+
+impl<C,S,T,V> Timeline<C,S,T,V> {
+    pub fn with_sampler(container: C, sampler: S) -> Self {
+        Timeline{
+            inner: container,
+            sampler,
+            _phantom: std::marker::PhantomData
+        }
+    }
+
+    pub fn sampler(&self) -> &S {
+        &self.sampler
+    }
+
+    /// Replace the sampler
+    /// WARNING this action may change timeline value everywhere
+    pub fn set_sampler(&mut self, sampler: S) {
+        self.sampler = sampler;
+    }
+
+    /// Get back the datastructure holding the data keys
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Iterator of the samples every `step` from the start to the end of `range` (ex: plotting, fixed-step physics),
+    /// walking the data keys once instead of searching them for each sample
+    ///
+    /// Same samples as [TimeLine::resample](../interface/trait.TimeLine.html#method.resample),
+    /// which adapters and generic code use, but faster on long ranges.
+    /// Yields nothing if `step` is not positive, and stops before the time overflows
+    pub fn resample_keys(&self, range: RangeInclusive<T>, step: T) -> Resample<'_,C,S,T,V>
+    where
+        C: DataContainer<T,V>,
+        T: Clone + Ord + CheckedAdd + Zero {
+        let (start, end) = range.into_inner();
+        Resample::new(&self.inner, &self.sampler, start, end, step)
+    }
+}
+
+impl<C,S,T,V> Timeline<C,S,T,V>
+where
+    C: DataContainer<T,V>,
+    T: PartialOrd {
+    /// The data keys the curve is extrapolated from, if `start` is before the first one or `end` after the last one
+    pub(crate) fn extrapolation_keys(&self, start: &T, end: &T) -> (Option<&T>, Option<&T>) {
+        let (mut past, mut future) = self.inner.split_at(start);
+        let first_key = match past.next() {
+            None => future.next().map(|(time, _)| time).filter(|time| *time > start),
+            Some(_) => None
+        };
+        let (mut past, mut future) = self.inner.split_at(end);
+        let last_key = match future.next() {
+            None => past.next().map(|(time, _)| time),
+            Some(_) => None
+        };
+        (first_key, last_key)
+    }
+}
+
+impl<C,S,T,V> Deref for Timeline<C,S,T,V> {
+    type Target = C;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<C,S,T,V> DerefMut for Timeline<C,S,T,V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<C,S,T,V> TimeLine<T> for Timeline<C,S,T,V>
+where
+    C: DataContainer<T,V> + DataContainerMut<T,V>,
+    S: DataSampler<T,V>,
+    T: Clone,
+    V: Clone {
+    type Item = V;
+    fn try_get_sample(&self, time: T) -> Result<Self::Item, SampleError> {
+        let (mut past, mut future) = self.inner.split_at(&time);
+        self.sampler.try_sample(&mut past, &mut future, time)
+    }
+    fn set_key(&mut self, time: T, value: Self::Item) {
+        self.inner.insert_key(time, value);
+    }
+    fn remove_key(&mut self, time: T) -> Option<Self::Item> {
+        self.inner.remove_key(&time)
+    }
+}
+
+impl<C,S,T,V> DerivativeTimeLine<T> for Timeline<C,S,T,V>
+where
+    C: DataContainer<T,V> + DataContainerMut<T,V>,
+    S: DerivativeSampler<T,V>,
+    T: Clone,
+    V: Clone {
+    type Derivative = S::Derivative;
+    fn try_get_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        let (mut past, mut future) = self.inner.split_at(&time);
+        self.sampler.try_sample_derivative(&mut past, &mut future, time)
+    }
+    fn try_get_second_derivative(&self, time: T) -> Result<Self::Derivative, SampleError> {
+        let (mut past, mut future) = self.inner.split_at(&time);
+        self.sampler.try_sample_second_derivative(&mut past, &mut future, time)
+    }
+}
@@ -0,0 +1,189 @@
+use crate::interface::{TimeLine, SampleError};
+use crate::samplers::Lerp;
+use num_traits::Float;
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::iter::FromIterator;
+use std::ops::RangeInclusive;
+use super::{CrossingTimeLine, Crossing};
+
+/// Collection of timelines identified by a key, that can be sampled all at once
+///
+/// Sampling the set at an instant gives a lazy iterator of `(key, value)`, nothing is allocated
+/// (ex: rewind every entity of a game server at the instant of a shot for lag compensation).
+/// The timelines that fail to sample are skipped, use `try_sample_all` to get the errors.
+///
+/// The samples can be filtered with `sample_filtered` (ex: entities near the shot), or only a subset
+/// of the timelines can be sampled with `sample_keys` (ex: candidates given by a spatial partition).
+///
+/// K: the key identifying a timeline (ex: entity id)
+/// TL: the timelines (ex: LerpBtreeTimeline)
+pub struct TimelineSet<K, TL> {
+    timelines: BTreeMap<K, TL>
+}
+
+impl<K: Ord, TL> TimelineSet<K, TL> {
+    pub fn new() -> Self {
+        TimelineSet {
+            timelines: BTreeMap::new()
+        }
+    }
+
+    /// Add a timeline, returning the timeline it replaced if any
+    pub fn insert(&mut self, key: K, timeline: TL) -> Option<TL> {
+        self.timelines.insert(key, timeline)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<TL> {
+        self.timelines.remove(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&TL> {
+        self.timelines.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut TL> {
+        self.timelines.get_mut(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.timelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timelines.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, K, TL> {
+        self.timelines.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, K, TL> {
+        self.timelines.iter_mut()
+    }
+
+    /// Sample every timeline at instant `time`, with the reason of the failures
+    pub fn try_sample_all<'a, T>(&'a self, time: T) -> impl Iterator<Item = (&'a K, Result<TL::Item, SampleError>)> + 'a
+    where
+        TL: TimeLine<T>,
+        T: Clone + 'a {
+        self.timelines.iter().map(move |(key, timeline)| (key, timeline.try_get_sample(time.clone())))
+    }
+
+    /// Sample every timeline at instant `time`, skipping the timelines that fail
+    pub fn sample_all<'a, T>(&'a self, time: T) -> impl Iterator<Item = (&'a K, TL::Item)> + 'a
+    where
+        TL: TimeLine<T>,
+        T: Clone + 'a {
+        self.timelines.iter().filter_map(move |(key, timeline)| timeline.get_sample(time.clone()).map(|value| (key, value)))
+    }
+
+    /// Sample every timeline at instant `time`, keeping the samples accepted by `filter`
+    pub fn sample_filtered<'a, T, F>(&'a self, time: T, mut filter: F) -> impl Iterator<Item = (&'a K, TL::Item)> + 'a
+    where
+        TL: TimeLine<T>,
+        T: Clone + 'a,
+        F: FnMut(&K, &TL::Item) -> bool + 'a {
+        self.sample_all(time).filter(move |(key, value)| filter(key, value))
+    }
+
+    /// Sample the timelines of `keys` at instant `time`, skipping the missing keys and the timelines that fail
+    pub fn sample_keys<'a, T, I>(&'a self, time: T, keys: I) -> impl Iterator<Item = (&'a K, TL::Item)> + 'a
+    where
+        TL: TimeLine<T>,
+        T: Clone + 'a,
+        I: IntoIterator<Item = &'a K>,
+        I::IntoIter: 'a {
+        keys.into_iter().filter_map(move |key| {
+            let (key, timeline) = self.timelines.get_key_value(key)?;
+            timeline.get_sample(time.clone()).map(|value| (key, value))
+        })
+    }
+
+    /// Instants in `range` at which each timeline crosses `threshold`, with the reason of the failures
+    /// (ex: the entities whose health dropped below 0 during the last tick)
+    #[allow(clippy::type_complexity)]
+    pub fn try_crossings_all<'a, T>(&'a self, range: RangeInclusive<T>, threshold: TL::Item)
+        -> impl Iterator<Item = (&'a K, Result<Vec<Crossing<TL::Item>>, SampleError>)> + 'a
+    where
+        TL: CrossingTimeLine<T>,
+        TL::Item: Float + 'a,
+        T: Clone + 'a {
+        self.timelines.iter().map(move |(key, timeline)| (key, timeline.try_crossings(range.clone(), threshold)))
+    }
+
+    /// Instants in `range` at which `predicate` changes on each timeline, with the reason of the failures
+    pub fn try_edges_all<'a, T, F, P>(&'a self, range: RangeInclusive<T>, mut predicate: P)
+        -> impl Iterator<Item = (&'a K, Result<Vec<Crossing<F>>, SampleError>)> + 'a
+    where
+        TL: CrossingTimeLine<T>,
+        TL::Item: Clone + Lerp<F>,
+        T: Clone + 'a,
+        F: Float,
+        P: FnMut(&TL::Item) -> bool + 'a {
+        self.timelines.iter().map(move |(key, timeline)| (key, timeline.try_edges(range.clone(), &mut predicate)))
+    }
+}
+
+impl<K: Ord, TL> Default for TimelineSet<K, TL> {
+    fn default() -> Self {
+        TimelineSet::new()
+    }
+}
+
+impl<K: Ord, TL> FromIterator<(K, TL)> for TimelineSet<K, TL> {
+    fn from_iter<I: IntoIterator<Item = (K, TL)>>(timelines: I) -> Self {
+        TimelineSet {
+            timelines: timelines.into_iter().collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timelines::LerpBtreeTimeline;
+
+    fn set() -> TimelineSet<&'static str, LerpBtreeTimeline<u32, f64, f64>> {
+        let timeline = |keys: &[(u32, f64)]| LerpBtreeTimeline::new(keys.iter().cloned().collect());
+        TimelineSet::from_iter(vec![
+            ("a", timeline(&[(0, 0.0), (10, 10.0)])),
+            ("b", timeline(&[(0, 100.0), (10, 80.0)])),
+            ("c", timeline(&[(0, 5.0)])),
+        ])
+    }
+
+    #[test]
+    fn sample_all() {
+        let set = set();
+        let samples: Vec<_> = set.sample_all(5).collect();
+        assert_eq!(samples, vec![(&"a", 5.0), (&"b", 90.0)]);
+        let errors: Vec<_> = set.try_sample_all(5).filter_map(|(key, sample)| sample.err().map(|error| (key, error))).collect();
+        assert_eq!(errors, vec![(&"c", SampleError::NotEnoughKeys)]);
+    }
+
+    #[test]
+    fn filters() {
+        let set = set();
+        let near: Vec<_> = set.sample_filtered(5, |_, position| *position < 50.0).collect();
+        assert_eq!(near, vec![(&"a", 5.0)]);
+        let candidates: Vec<_> = set.sample_keys(10, &["b", "c", "d"]).collect();
+        assert_eq!(candidates, vec![(&"b", 80.0)]);
+    }
+
+    #[test]
+    fn crossings_all() {
+        use crate::timelines::Edge;
+        let set = set();
+        let crossings: Vec<_> = set.try_crossings_all(0..=10, 7.5).collect();
+        assert_eq!(crossings, vec![
+            (&"a", Ok(vec![Crossing { time: 7.5, edge: Edge::Rising }])),
+            (&"b", Ok(vec![])),
+            (&"c", Err(SampleError::NotEnoughKeys))
+        ]);
+        let edges: Vec<_> = set.try_edges_all::<_, f64, _>(0..=10, |position: &f64| *position > 85.0)
+            .filter_map(|(key, edges)| Some((key, edges.ok()?.len())))
+            .collect();
+        assert_eq!(edges, vec![(&"a", 0), (&"b", 1)]);
+    }
+}